@@ -1,25 +1,223 @@
-use std::{io::Write, path::Path, process::Stdio};
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{ExitCode, Stdio},
+};
 
 use anyhow::{bail, ensure, Context};
 
+use flate2::read::GzDecoder;
 use octocrab::models::repos::Asset;
-use reqwest::{Response, Url};
+use reqwest::Response;
+use sha2::{Digest, Sha256};
 use tokio::{
     fs::{self, File},
     io::{AsyncWriteExt, BufWriter},
     process::Command,
 };
 use vexmason::{
+    bundle::{extract_bundle, BUNDLE_VEXCOM_DIR},
     check_versions,
+    compile_file::{is_commit_hash, PYTHON_COMPILER_REVISION, PYTHON_COMPILER_REVISION_FILE},
+    hook::{hook_all, installed_shim_path, is_hooked, sync_home_file},
     installation_location::{
-        get_installation_path, get_user_directory, VEXCOM_NAME, VEXCOM_OLD_NAME,
+        arch_dir_name, default_installation_path, find_vexcode_extensions, get_installation_path,
+        get_vscode_extensions_directory, release_asset_name, vexcom_directory, HOME_ENV_VAR,
+        HOME_FILE_NAME, RELEASE_BINARIES, VEXCOM_BACKUP_NAME, VEXCOM_NAME, VEXCOM_OLD_NAME,
     },
 };
 
 const GITHUB_RELEASE_OWNER: &str = "zabackary";
 const GITHUB_RELEASE_REPO: &str = "vexmason";
 
-const VEXCOM_TMP_NAME: &str = "vexcom.tmp";
+const CHECKSUMS_ASSET_NAME: &str = "checksums.txt";
+/// where binaries are downloaded to before they replace the `bin` directory
+const BIN_STAGING_DIR_NAME: &str = "bin.new";
+/// where an offline bundle is extracted to before it's installed
+const BUNDLE_STAGING_DIR_NAME: &str = "bundle.tmp";
+
+const USAGE: &str = "usage: vexmason-installer [options]
+
+options:
+  --yes, -y                      don't ask for confirmation
+  --prefix DIR                   install into DIR instead of the default
+                                 directory, and remember it for vexcom
+  --vscode-extensions-dir DIR    look for the VEXcode extension in DIR
+  --version TAG                  install the release TAG instead of the latest
+  --offline-bundle FILE          install from a bundle made by `vexmason bundle`
+  --python-compiler-source SRC   install python-compiler with `git` or as an
+                                 `archive` (the default is git if it's installed)
+  --uninstall                    remove vexmason and restore vexcom
+
+exit codes:
+  1  other failure
+  2  invalid arguments
+  3  python or git is missing or too old
+  4  GitHub couldn't be reached
+  5  a download or bundle failed verification
+  6  the VEXcode extension isn't installed
+  7  files couldn't be installed
+";
+
+/// The kinds of failures the installer tells apart with its exit code, so
+/// scripts can react to them.
+#[derive(Debug, Clone, Copy)]
+enum FailureKind {
+    Usage = 2,
+    Requirements = 3,
+    Network = 4,
+    Verification = 5,
+    VexcodeMissing = 6,
+    Filesystem = 7,
+}
+
+impl FailureKind {
+    /// Gets the kind from the exit code of another installer.
+    fn from_exit_code(code: i32) -> Option<Self> {
+        [
+            FailureKind::Usage,
+            FailureKind::Requirements,
+            FailureKind::Network,
+            FailureKind::Verification,
+            FailureKind::VexcodeMissing,
+            FailureKind::Filesystem,
+        ]
+        .into_iter()
+        .find(|kind| *kind as i32 == code)
+    }
+}
+
+impl std::fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FailureKind::Usage => write!(f, "invalid arguments"),
+            FailureKind::Requirements => write!(f, "a requirement isn't installed"),
+            FailureKind::Network => write!(f, "failed to download vexmason"),
+            FailureKind::Verification => write!(f, "failed to verify the downloaded files"),
+            FailureKind::VexcodeMissing => write!(f, "the VEXcode extension wasn't found"),
+            FailureKind::Filesystem => write!(f, "failed to install files"),
+        }
+    }
+}
+
+/// How python-compiler is downloaded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CompilerSource {
+    /// a shallow git checkout, which can be updated in place
+    Git,
+    /// the source archive GitHub makes for every revision, so git isn't needed
+    Archive,
+}
+
+#[derive(Debug, Default)]
+struct InstallerOptions {
+    /// set when started by another installer, which handles pausing at exit
+    subprocess: bool,
+    uninstall: bool,
+    help: bool,
+    /// never wait for ENTER
+    yes: bool,
+    offline_bundle: Option<PathBuf>,
+    /// the installation directory, if it isn't the default
+    prefix: Option<PathBuf>,
+    vscode_extensions_dir: Option<PathBuf>,
+    version: Option<String>,
+    /// picked automatically if not given
+    python_compiler_source: Option<CompilerSource>,
+}
+
+impl InstallerOptions {
+    fn parse(args: Vec<String>) -> anyhow::Result<Self> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg, None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .with_context(|| format!("{} requires a value", flag))
+            };
+            match flag.as_str() {
+                "--subprocess" => options.subprocess = true,
+                "--uninstall" => options.uninstall = true,
+                "--help" | "-h" => options.help = true,
+                "--yes" | "-y" => options.yes = true,
+                "--offline-bundle" => options.offline_bundle = Some(value()?.into()),
+                "--prefix" => options.prefix = Some(std::path::absolute(value()?)?),
+                "--vscode-extensions-dir" => {
+                    options.vscode_extensions_dir = Some(std::path::absolute(value()?)?);
+                }
+                "--version" => {
+                    let version = value()?;
+                    options.version = Some(if version.starts_with('v') {
+                        version
+                    } else {
+                        format!("v{}", version)
+                    });
+                }
+                "--python-compiler-source" => {
+                    options.python_compiler_source = Some(match value()?.as_str() {
+                        "git" => CompilerSource::Git,
+                        "archive" => CompilerSource::Archive,
+                        source => bail!(
+                            "unknown python-compiler source '{}'. it must be `git` or `archive`",
+                            source
+                        ),
+                    });
+                }
+                _ => bail!("unknown option '{}'\n\n{}", flag, USAGE),
+            }
+        }
+        Ok(options)
+    }
+
+    /// Arguments for another installer that's started to do the installation.
+    fn subprocess_args(&self) -> Vec<String> {
+        let mut args = vec!["--subprocess".to_string()];
+        if self.yes {
+            args.push("--yes".to_string());
+        }
+        if let Some(prefix) = &self.prefix {
+            args.push("--prefix".to_string());
+            args.push(prefix.to_string_lossy().to_string());
+        }
+        if let Some(dir) = &self.vscode_extensions_dir {
+            args.push("--vscode-extensions-dir".to_string());
+            args.push(dir.to_string_lossy().to_string());
+        }
+        if let Some(source) = self.python_compiler_source {
+            args.push("--python-compiler-source".to_string());
+            args.push(
+                match source {
+                    CompilerSource::Git => "git",
+                    CompilerSource::Archive => "archive",
+                }
+                .to_string(),
+            );
+        }
+        args
+    }
+
+    fn vscode_extensions_dir(&self) -> anyhow::Result<PathBuf> {
+        match &self.vscode_extensions_dir {
+            Some(dir) => Ok(dir.clone()),
+            None => get_vscode_extensions_directory(std::env::current_exe().ok().as_deref()),
+        }
+    }
+
+    fn pause(&self, msg: &str) {
+        if !self.yes {
+            pause(msg);
+        }
+    }
+}
 
 fn pause(msg: &str) {
     let stdin = std::io::stdin();
@@ -31,77 +229,170 @@ fn pause(msg: &str) {
     let _ = stdin.read_line(&mut String::new()).unwrap();
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let subprocess = std::env::args().skip(1).next() == Some("--subprocess".to_string());
-    match body(subprocess).await {
+fn main() -> ExitCode {
+    let options = match InstallerOptions::parse(std::env::args().skip(1).collect()) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{err:#}");
+            return ExitCode::from(FailureKind::Usage as u8);
+        }
+    };
+    if options.help {
+        print!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    if let Some(prefix) = &options.prefix {
+        // everything in this process finds the installation through this. it's
+        // set before the runtime starts any threads
+        std::env::set_var(HOME_ENV_VAR, prefix);
+    }
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            eprintln!("failed to start the async runtime: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    runtime.block_on(run(options))
+}
+
+async fn run(options: InstallerOptions) -> ExitCode {
+    let result = if options.uninstall {
+        uninstall_body(&options).await
+    } else if let Some(bundle) = &options.offline_bundle {
+        offline_body(bundle, &options).await
+    } else {
+        body(&options).await
+    };
+    match result {
         Ok(_) => {
-            println!("Installation has finished successfully.");
-            if !subprocess {
-                pause("Press ENTER to exit...");
+            if options.uninstall {
+                println!("Uninstallation has finished successfully.");
+            } else {
+                println!("Installation has finished successfully.");
             }
-            Ok(())
+            if !options.subprocess {
+                options.pause("Press ENTER to exit...");
+            }
+            ExitCode::SUCCESS
         }
         Err(err) => {
             eprintln!("Something went wrong! Try re-running the installer. If that doesn't work, create a GitHub issue.\n{err:?}");
-            if !subprocess {
-                pause("\nPress ENTER to exit");
+            if !options.subprocess {
+                options.pause("\nPress ENTER to exit");
+            }
+            match err.downcast_ref::<FailureKind>() {
+                Some(kind) => ExitCode::from(*kind as u8),
+                None => ExitCode::FAILURE,
             }
-            Err(err)
         }
     }
 }
 
-async fn body(subprocess: bool) -> anyhow::Result<()> {
+async fn body(options: &InstallerOptions) -> anyhow::Result<()> {
     ensure!(!cfg!(target_os = "macos"), "At this time, MacOS is not supported. if you would like to support it, create a GitHub issue.");
     // ensure!(cfg!(target_os = "windows"), "At this time, Windows is the only supported OS. Please create a GitHub issue if you would like to help support another.");
 
-    check_versions::check_versions()?;
+    let compiler_source = match options.python_compiler_source {
+        Some(source) => source,
+        None if check_versions::check_git_version().is_ok() => CompilerSource::Git,
+        None => {
+            println!("git isn't installed, so python-compiler will be downloaded as an archive.");
+            CompilerSource::Archive
+        }
+    };
+    check_versions::check_versions(compiler_source == CompilerSource::Git)
+        .context(FailureKind::Requirements)?;
 
-    let version = format!("v{}", env!("CARGO_PKG_VERSION"));
+    let mut version = format!("v{}", env!("CARGO_PKG_VERSION"));
     println!(
         "Welcome to the vexmason installation wizard for {}.",
         version
     );
-    if subprocess {
+    if options.subprocess {
         println!("This installer was started as a subprocess.");
     }
     let octocrab = octocrab::instance();
     let repo = octocrab.repos(GITHUB_RELEASE_OWNER, GITHUB_RELEASE_REPO);
     let releases = repo.releases();
-    println!("Checking for the latest updates...");
-    let latest_release = releases.get_latest().await?;
-    if latest_release.tag_name != version {
-        println!("It looks like there's a newer version of the installer associated with the latest version {}.", latest_release.tag_name);
-        println!("Downloading it to be used...");
-        let mut installer_asset = None;
-        for asset in latest_release.assets {
-            if asset.name == "vexmason-installer.exe" {
-                installer_asset = Some(asset);
-            }
+    let target_release = match &options.version {
+        Some(tag) => {
+            println!("Looking up {}...", tag);
+            releases
+                .get_by_tag(tag)
+                .await
+                .with_context(|| format!("there's no release {} on GitHub", tag))
+                .context(FailureKind::Network)?
+        }
+        None => {
+            println!("Checking for the latest updates...");
+            releases.get_latest().await.context(FailureKind::Network)?
         }
+    };
+    if target_release.tag_name != version {
+        let target_checksums = fetch_checksums(&target_release.assets).await?;
+        println!(
+            "It looks like there's a different version of the installer associated with {}.",
+            target_release.tag_name
+        );
+        println!("Downloading it to be used...");
+        let installer_asset_name = release_asset_name("vexmason-installer")?;
+        let installer_asset = target_release
+            .assets
+            .iter()
+            .find(|asset| asset.name == installer_asset_name);
         if let Some(installer_asset) = installer_asset {
-            let filename = format!("vexmason-installer-{}.exe", latest_release.tag_name,);
+            let filename = format!(
+                "vexmason-installer-{}{}",
+                target_release.tag_name,
+                std::env::consts::EXE_SUFFIX
+            );
             let dir = std::env::temp_dir();
             let path = dir.join(&filename);
-            if path.try_exists()? {
-                bail!("It seems like the file {} in {} already exists. If that's the installer for the new version, run that directly.", filename, dir.to_string_lossy());
+            if path.try_exists().context(FailureKind::Filesystem)? {
+                return Err(anyhow::anyhow!("It seems like the file {} in {} already exists. If that's the installer for the new version, run that directly.", filename, dir.to_string_lossy())
+                    .context(FailureKind::Filesystem));
             }
-            install_bin(&filename, &installer_asset.browser_download_url, &dir).await?;
+            install_bin(installer_asset, &filename, &dir, &target_checksums).await?;
             println!("Downloaded.");
-            println!("\n--- {} installer output ---\n", latest_release.tag_name);
+            println!("\n--- {} installer output ---\n", target_release.tag_name);
+            // the new installer isn't next to a recorded installation
+            // directory, so it's passed on like `--prefix` would be
+            let installation_directory =
+                get_installation_path(std::env::current_exe().ok().as_deref())
+                    .context(FailureKind::Filesystem)?;
             let mut child = Command::new(&path)
-                .arg("--subprocess")
+                .args(options.subprocess_args())
+                .env(HOME_ENV_VAR, installation_directory)
                 .stdout(Stdio::inherit())
                 .stderr(Stdio::inherit())
                 .spawn()
-                .with_context(|| "failed to spawn new installer")?;
-            child.wait().await?;
-            println!("\n--- finished {} installer ---", latest_release.tag_name);
+                .with_context(|| "failed to spawn new installer")
+                .context(FailureKind::Filesystem)?;
+            let exit_status = child.wait().await?;
+            println!("\n--- finished {} installer ---", target_release.tag_name);
             println!("Removing installer...");
-            fs::remove_file(path).await?;
+            fs::remove_file(path)
+                .await
+                .with_context(|| "failed to remove the downloaded installer")
+                .context(FailureKind::Filesystem)?;
             println!("Removed.");
+            if !exit_status.success() {
+                let err = anyhow::anyhow!("the {} installer failed", target_release.tag_name);
+                return Err(
+                    match exit_status.code().and_then(FailureKind::from_exit_code) {
+                        Some(kind) => err.context(kind),
+                        None => err,
+                    },
+                );
+            }
             return Ok(());
+        } else if options.version.is_some() {
+            eprintln!(
+                "Failed to find the installer for {}, installing it with this installer instead",
+                target_release.tag_name
+            );
+            version = target_release.tag_name.clone();
         } else {
             eprintln!(
                 "Failed to find the latest installer, falling back to an outdated install of {}",
@@ -110,25 +401,194 @@ async fn body(subprocess: bool) -> anyhow::Result<()> {
         }
     }
 
-    let installation_directory = get_installation_path(std::env::current_exe().ok().as_deref())?;
-    let already_installed = installation_directory.try_exists()?;
-    if already_installed {
+    let installation_directory = get_installation_path(std::env::current_exe().ok().as_deref())
+        .context(FailureKind::Filesystem)?;
+    confirm_installation(&installation_directory, options)?;
+    println!("Downloading release metadata for {}...", version);
+    let release = if target_release.tag_name == version {
+        target_release
+    } else {
+        releases
+            .get_by_tag(&version)
+            .await
+            .with_context(||anyhow::anyhow!("It seems like there's no release on GitHub yet for this version ({}). Maybe someone forgot to upload it.", version))
+            .context(FailureKind::Network)?
+    };
+    let checksums = fetch_checksums(&release.assets).await?;
+    println!("> Downloaded.");
+
+    println!("Looking for VEXcode extensions...");
+    println!("> arch_dir_name: {}", arch_dir_name()?);
+    let vscode_extensions_dir = options.vscode_extensions_dir()?;
+    ensure_vexcode_installed(&vscode_extensions_dir).await?;
+
+    // everything is downloaded and verified before anything is replaced, so a
+    // failed download leaves the previous installation untouched
+    let bin_dir = installation_directory.join("bin");
+    let staging_bin_dir = installation_directory.join(BIN_STAGING_DIR_NAME);
+    println!("Downloading binaries...");
+    create_staging_dir(&staging_bin_dir)?;
+    // only this platform's binaries are installed. a copy of the shim is kept
+    // in the installation to hook extensions that are installed later
+    for binary in RELEASE_BINARIES {
+        let asset_name = release_asset_name(binary)?;
+        let Some(asset) = release.assets.iter().find(|asset| asset.name == asset_name) else {
+            remove_staging_dir(&staging_bin_dir)?;
+            return Err(anyhow::anyhow!(
+                "the release doesn't have {} for this platform ({}). it might not be supported yet",
+                asset_name,
+                arch_dir_name()?
+            )
+            .context(FailureKind::Network));
+        };
+        let filename = format!("{}{}", binary, std::env::consts::EXE_SUFFIX);
+        if let Err(err) = install_bin(asset, &filename, &staging_bin_dir, &checksums).await {
+            remove_staging_dir(&staging_bin_dir)?;
+            return Err(err);
+        }
+    }
+    write_home_file(&staging_bin_dir, &installation_directory).await?;
+
+    println!("Installing python-compiler...");
+    let compiler_dir = installation_directory.join("lib").join("python-compiler");
+    match compiler_source {
+        CompilerSource::Git => {
+            println!(
+                "> Checking out python-compiler {}...",
+                PYTHON_COMPILER_REVISION
+            );
+            install_git_lib(
+                &compiler_dir,
+                "https://github.com/zabackary/python-compiler.git",
+                PYTHON_COMPILER_REVISION,
+            )
+            .await
+            .context(FailureKind::Network)?;
+        }
+        CompilerSource::Archive => {
+            println!(
+                "> Downloading python-compiler {}...",
+                PYTHON_COMPILER_REVISION
+            );
+            install_archive_lib(
+                &compiler_dir,
+                &format!(
+                    "https://github.com/zabackary/python-compiler/archive/{}.tar.gz",
+                    PYTHON_COMPILER_REVISION
+                ),
+                PYTHON_COMPILER_REVISION,
+            )
+            .await?;
+        }
+    }
+
+    println!("Installing binaries...");
+    replace_dir(&staging_bin_dir, &bin_dir)?;
+    println!("> Installed.");
+
+    hook_extensions(&vscode_extensions_dir, &installation_directory).await
+}
+
+/// Installs everything from an offline bundle created with `vexmason bundle`,
+/// without touching the network.
+async fn offline_body(bundle: &Path, options: &InstallerOptions) -> anyhow::Result<()> {
+    ensure!(!cfg!(target_os = "macos"), "At this time, MacOS is not supported. if you would like to support it, create a GitHub issue.");
+
+    // git isn't needed since python-compiler comes from the bundle
+    check_versions::check_versions(false).context(FailureKind::Requirements)?;
+
+    println!(
+        "Welcome to the vexmason offline installation wizard for v{}.",
+        env!("CARGO_PKG_VERSION")
+    );
+    if options.subprocess {
+        println!("This installer was started as a subprocess.");
+    }
+    let bundle = dunce::canonicalize(bundle)
+        .with_context(|| format!("can't find the bundle {}", bundle.to_string_lossy()))
+        .context(FailureKind::Usage)?;
+    let installation_directory = get_installation_path(std::env::current_exe().ok().as_deref())
+        .context(FailureKind::Filesystem)?;
+    confirm_installation(&installation_directory, options)?;
+
+    println!("Extracting and verifying {}...", bundle.to_string_lossy());
+    let staging_dir = installation_directory.join(BUNDLE_STAGING_DIR_NAME);
+    if staging_dir.try_exists().context(FailureKind::Filesystem)? {
+        remove_staging_dir(&staging_dir)?;
+    }
+    std::fs::create_dir_all(&installation_directory)
+        .with_context(|| {
+            format!(
+                "failed to create {}",
+                installation_directory.to_string_lossy()
+            )
+        })
+        .context(FailureKind::Filesystem)?;
+    let manifest = match extract_bundle(&bundle, &staging_dir) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            remove_staging_dir(&staging_dir)?;
+            return Err(err.context(FailureKind::Verification));
+        }
+    };
+    println!(
+        "> Verified {} files from vexmason v{}.",
+        manifest.checksums.len(),
+        manifest.version
+    );
+
+    let vscode_extensions_dir = options.vscode_extensions_dir()?;
+    ensure_vexcode_installed(&vscode_extensions_dir).await?;
+    let staging_shim = installed_shim_path(&staging_dir);
+    if !staging_shim.exists() {
+        // the shim is stored separately in the bundle
+        fs::copy(
+            staging_dir.join(BUNDLE_VEXCOM_DIR).join(VEXCOM_NAME),
+            &staging_shim,
+        )
+        .await
+        .with_context(|| "failed to copy the vexcom shim out of the bundle")
+        .context(FailureKind::Filesystem)?;
+    }
+    write_home_file(&staging_dir.join("bin"), &installation_directory).await?;
+
+    println!("Installing python-compiler...");
+    let lib_dir = installation_directory.join("lib");
+    std::fs::create_dir_all(&lib_dir)
+        .with_context(|| format!("failed to create {}", lib_dir.to_string_lossy()))
+        .context(FailureKind::Filesystem)?;
+    replace_dir(
+        &staging_dir.join("lib").join("python-compiler"),
+        &lib_dir.join("python-compiler"),
+    )?;
+
+    println!("Installing binaries...");
+    replace_dir(
+        &staging_dir.join("bin"),
+        &installation_directory.join("bin"),
+    )?;
+    remove_staging_dir(&staging_dir)?;
+    println!("> Installed.");
+
+    hook_extensions(&vscode_extensions_dir, &installation_directory).await
+}
+
+/// Asks the user to confirm installing into (or updating) `installation_directory`.
+fn confirm_installation(
+    installation_directory: &Path,
+    options: &InstallerOptions,
+) -> anyhow::Result<()> {
+    if installation_directory.try_exists()? {
         // try to update
         println!("Already installed, attempting update");
-        pause(&format!(
+        options.pause(&format!(
             "> Press ENTER to update installation at {}",
             installation_directory
                 .to_str()
                 .unwrap_or("unknown directory")
         ));
-        if installation_directory.join("bin").try_exists()? {
-            println!("> Uninstalling...");
-            std::fs::remove_dir_all(installation_directory.join("bin"))?;
-        } else {
-            println!("> bin directory does not exist, skipping uninstall step");
-        }
     } else {
-        pause("Press ENTER to start installation...");
+        options.pause("Press ENTER to start installation...");
         println!(
             "> Installing vexmason into {}",
             installation_directory
@@ -136,172 +596,527 @@ async fn body(subprocess: bool) -> anyhow::Result<()> {
                 .unwrap_or("unknown directory")
         );
     }
-    println!("Downloading release metadata for {}...", version);
-    let release = releases
-        .get_by_tag(&version)
-        .await
-        .with_context(||anyhow::anyhow!("It seems like there's no release on GitHub yet for this version ({}). Maybe someone forgot to upload it.", version))?;
-    println!("> Downloaded.");
+    Ok(())
+}
 
-    println!("Installing python-compiler...");
-    std::fs::create_dir_all(installation_directory.join("bin"))?;
-    let compiler_dir = installation_directory.join("lib").join("python-compiler");
-    if compiler_dir.try_exists()? {
-        println!("> python-compiler seems to be cloned already, pulling the latest changes...");
-        pull_git_lib(&compiler_dir).await?;
-    } else {
-        println!("> Cloning python-compiler...");
-        install_git_lib(
-            &compiler_dir,
-            "https://github.com/zabackary/python-compiler.git",
-        )
-        .await?;
+async fn ensure_vexcode_installed(vscode_extensions_dir: &Path) -> anyhow::Result<()> {
+    let extensions = find_vexcode_extensions(vscode_extensions_dir)
+        .await
+        .context(FailureKind::VexcodeMissing)?;
+    if extensions.is_empty() {
+        return Err(
+            anyhow::anyhow!("no vexcode extension found").context(FailureKind::VexcodeMissing)
+        );
     }
+    for extension in extensions {
+        println!(
+            "> VEXCode extension location: {}",
+            extension.file_name().unwrap_or_default().to_string_lossy()
+        );
+    }
+    Ok(())
+}
 
+/// Installs the shim from the installation into every VEXcode extension that
+/// isn't hooked yet, and updates where the hooked ones find the installation.
+async fn hook_extensions(
+    vscode_extensions_dir: &Path,
+    installation_directory: &Path,
+) -> anyhow::Result<()> {
     println!("Installing vexcom hook...");
-    let arch_dir_name = if cfg!(all(
-        target_os = "windows",
-        any(target_arch = "x86", target_arch = "x86_64")
-    )) {
-        "win32"
-    } else if cfg!(all(target_os = "linux", target_arch = "arm")) {
-        "linux-arm32"
-    } else if cfg!(all(target_os = "linux", target_arch = "aarch64")) {
-        "linux-arm64"
-    } else if cfg!(all(
-        target_os = "linux",
-        any(target_arch = "x86", target_arch = "x86_64")
-    )) {
-        "linux-x86"
-    } else if cfg!(target_os = "macos") {
-        "osx"
-    } else {
-        bail!("unsupported operating system")
-    };
-    println!("> arch_dir_name: {}", arch_dir_name);
-    let vscode_extensions_dir = get_user_directory(std::env::current_exe().ok().as_deref())?
-        .join(".vscode")
-        .join("extensions");
-    let mut dir_iterator = tokio::fs::read_dir(&vscode_extensions_dir).await?;
-    let vexcode_subdir = loop {
-        let entry = dir_iterator
-            .next_entry()
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("no vexcode extension found"))?;
-        if entry
-            .file_name()
-            .to_string_lossy()
-            .starts_with("vexrobotics.vexcode")
-        {
-            break entry.file_name();
+    let shim = installed_shim_path(installation_directory);
+    for extension in find_vexcode_extensions(vscode_extensions_dir)
+        .await
+        .context(FailureKind::VexcodeMissing)?
+    {
+        let vexcom_dir = vexcom_directory(&extension).context(FailureKind::VexcodeMissing)?;
+        if is_hooked(&vexcom_dir) {
+            sync_home_file(&vexcom_dir, &shim)
+                .await
+                .context(FailureKind::Filesystem)?;
         }
-    };
+    }
+    let hooked = hook_all(vscode_extensions_dir, &shim)
+        .await
+        .context(FailureKind::Filesystem)?;
+    if hooked.is_empty() {
+        println!("> vexcom hook already installed in every extension, skipping");
+    }
+    for extension in hooked {
+        println!(
+            "> hooked {}",
+            extension.file_name().unwrap_or_default().to_string_lossy()
+        );
+    }
+    Ok(())
+}
+
+/// Creates an empty staging directory at `path`, removing a leftover one from
+/// an earlier failed installation.
+fn create_staging_dir(path: &Path) -> anyhow::Result<()> {
+    if path.try_exists().context(FailureKind::Filesystem)? {
+        remove_staging_dir(path)?;
+    }
+    std::fs::create_dir_all(path)
+        .with_context(|| format!("failed to create {}", path.to_string_lossy()))
+        .context(FailureKind::Filesystem)
+}
+
+fn remove_staging_dir(path: &Path) -> anyhow::Result<()> {
+    std::fs::remove_dir_all(path)
+        .with_context(|| format!("failed to remove {}", path.to_string_lossy()))
+        .context(FailureKind::Filesystem)
+}
+
+/// Records the installation directory next to the binaries in `bin_dir` if it
+/// isn't the default, so the shim can find it without `VEXMASON_HOME`.
+/// Otherwise, removes a file left by an earlier installation (or a bundle), so
+/// it can't point the shim somewhere else. Hooked extensions get the change
+/// when they're synced with the installed shim.
+async fn write_home_file(bin_dir: &Path, installation_directory: &Path) -> anyhow::Result<()> {
+    let path = bin_dir.join(HOME_FILE_NAME);
+    if is_default_installation(installation_directory)? {
+        if path.try_exists().context(FailureKind::Filesystem)? {
+            fs::remove_file(&path)
+                .await
+                .with_context(|| format!("failed to remove {}", path.to_string_lossy()))
+                .context(FailureKind::Filesystem)?;
+        }
+        return Ok(());
+    }
+    fs::write(&path, installation_directory.to_string_lossy().as_bytes())
+        .await
+        .with_context(|| format!("failed to write {}", path.to_string_lossy()))
+        .context(FailureKind::Filesystem)
+}
+
+fn is_default_installation(installation_directory: &Path) -> anyhow::Result<bool> {
+    let default = default_installation_path(std::env::current_exe().ok().as_deref())
+        .context(FailureKind::Filesystem)?;
+    Ok(installation_directory == default)
+}
+
+/// Moves `new` into place at `old`, removing whatever was there before.
+fn replace_dir(new: &Path, old: &Path) -> anyhow::Result<()> {
+    if old.try_exists().context(FailureKind::Filesystem)? {
+        println!("> Removing previous {}...", old.to_string_lossy());
+        std::fs::remove_dir_all(old)
+            .with_context(|| format!("failed to remove {}", old.to_string_lossy()))
+            .context(FailureKind::Filesystem)?;
+    }
+    std::fs::rename(new, old)
+        .with_context(|| format!("failed to move {} into place", new.to_string_lossy()))
+        .context(FailureKind::Filesystem)
+}
+
+/// Restores the original vexcom in every hooked VEXcode extension and removes
+/// the installation directory.
+async fn uninstall_body(options: &InstallerOptions) -> anyhow::Result<()> {
     println!(
-        "> VEXCode extension location: {}",
-        vexcode_subdir.to_string_lossy()
+        "Welcome to the vexmason uninstallation wizard for v{}.",
+        env!("CARGO_PKG_VERSION")
     );
-    let vexcom_dir = vscode_extensions_dir
-        .join(vexcode_subdir)
-        .join("resources")
-        .join("tools")
-        .join("vexcom")
-        .join(arch_dir_name);
-    if vexcom_dir.join(VEXCOM_OLD_NAME).exists() {
-        println!("> vexcom hook already installed, skipping")
+    let installation_directory = get_installation_path(std::env::current_exe().ok().as_deref())
+        .context(FailureKind::Filesystem)?;
+    let vscode_extensions_dir = options.vscode_extensions_dir()?;
+    if !options.subprocess {
+        options.pause("Press ENTER to uninstall vexmason...");
+    }
+    // Windows can't remove a running executable, so removing `bin` would fail
+    // after the hooks are already gone
+    if cfg!(target_os = "windows") && is_running_from(&installation_directory.join("bin")) {
+        return uninstall_from_copy(options, &installation_directory).await;
+    }
+
+    let mut changes: Vec<String> = Vec::new();
+
+    println!("Removing vexcom hooks...");
+    let vexcode_extensions = if vscode_extensions_dir.try_exists()? {
+        find_vexcode_extensions(&vscode_extensions_dir).await?
     } else {
-        let mut found_asset: Option<&Asset> = None;
-        for asset in &release.assets {
-            if asset.name == "vexcom.exe" {
-                found_asset = Some(asset);
-            }
+        Vec::new()
+    };
+    if vexcode_extensions.is_empty() {
+        println!("> no VEXcode extensions found, skipping");
+    }
+    for vexcode_extension in vexcode_extensions {
+        let extension_name = vexcode_extension
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let vexcom_dir = vexcom_directory(&vexcode_extension)?;
+        if !vexcom_dir.join(VEXCOM_OLD_NAME).try_exists()? {
+            println!("> {}: not hooked, skipping", extension_name);
+            continue;
         }
-        if let Some(asset) = found_asset {
-            println!("> making a backup of {VEXCOM_NAME}...");
-            fs::copy(vexcom_dir.join(VEXCOM_NAME), vexcom_dir.join("vexcom.bak")).await?;
-            println!("> found artifact, downloading to {VEXCOM_TMP_NAME}...");
-            let mut response = reqwest::get(asset.browser_download_url.clone())
+        println!(
+            "> {}: restoring the original {}...",
+            extension_name, VEXCOM_NAME
+        );
+        if vexcom_dir.join(VEXCOM_NAME).try_exists()? {
+            fs::remove_file(vexcom_dir.join(VEXCOM_NAME))
                 .await
-                .with_context(|| "failed to fetch artifact")?
-                .error_for_status()
-                .with_context(|| "failed to fetch artifact")?;
-            write_chunks(
-                &mut response,
-                &mut File::create(vexcom_dir.join(VEXCOM_TMP_NAME))
-                    .await
-                    .with_context(|| "failed to create tmp file to read download")?,
-            )
-            .await
-            .with_context(|| "failed to copy download content")?;
-            println!("> installing hook...");
-            fs::rename(
-                vexcom_dir.join(VEXCOM_NAME),
-                vexcom_dir.join(VEXCOM_OLD_NAME),
-            )
-            .await?;
-            fs::rename(
-                vexcom_dir.join(VEXCOM_TMP_NAME),
-                vexcom_dir.join(VEXCOM_NAME),
-            )
-            .await?;
-            println!("> successfully installed.");
-        } else {
-            bail!("can't find vexcom.exe artifact in latest release");
+                .with_context(|| "failed to remove the vexcom shim")?;
+            changes.push(format!(
+                "removed the vexcom shim {}",
+                vexcom_dir.join(VEXCOM_NAME).to_string_lossy()
+            ));
+        }
+        fs::rename(
+            vexcom_dir.join(VEXCOM_OLD_NAME),
+            vexcom_dir.join(VEXCOM_NAME),
+        )
+        .await
+        .with_context(|| format!("failed to restore {}", VEXCOM_OLD_NAME))?;
+        changes.push(format!(
+            "renamed {} to {}",
+            vexcom_dir.join(VEXCOM_OLD_NAME).to_string_lossy(),
+            VEXCOM_NAME
+        ));
+        if vexcom_dir.join(HOME_FILE_NAME).try_exists()? {
+            fs::remove_file(vexcom_dir.join(HOME_FILE_NAME)).await?;
+            changes.push(format!(
+                "removed {}",
+                vexcom_dir.join(HOME_FILE_NAME).to_string_lossy()
+            ));
+        }
+        if vexcom_dir.join(VEXCOM_BACKUP_NAME).try_exists()? {
+            // identical to the file that was just restored
+            fs::remove_file(vexcom_dir.join(VEXCOM_BACKUP_NAME)).await?;
+            changes.push(format!(
+                "removed the backup {}",
+                vexcom_dir.join(VEXCOM_BACKUP_NAME).to_string_lossy()
+            ));
         }
     }
-    let bin_dir = installation_directory.join("bin");
-    println!("Downloading binaries...");
-    for asset in release.assets {
-        if asset.name != "vexcom.exe" {
-            install_bin(&asset.name, &asset.browser_download_url, &bin_dir).await?;
+
+    println!("Removing the installation...");
+    for subdirectory in ["bin", "lib"] {
+        let path = installation_directory.join(subdirectory);
+        if path.try_exists()? {
+            std::fs::remove_dir_all(&path)
+                .with_context(|| format!("failed to remove {}", path.to_string_lossy()))?;
+            changes.push(format!("removed {}", path.to_string_lossy()));
+        } else {
+            println!("> {} does not exist, skipping", path.to_string_lossy());
         }
     }
+    if installation_directory.try_exists()?
+        && std::fs::read_dir(&installation_directory)?.next().is_none()
+    {
+        std::fs::remove_dir(&installation_directory)?;
+        changes.push(format!(
+            "removed {}",
+            installation_directory.to_string_lossy()
+        ));
+    }
+
+    println!("\nSummary of changes:");
+    if changes.is_empty() {
+        println!("> nothing to do, vexmason doesn't seem to be installed");
+    }
+    for change in changes {
+        println!("> {}", change);
+    }
     Ok(())
 }
 
-async fn install_bin(filename: &str, url: &Url, dir: &Path) -> anyhow::Result<()> {
+/// Whether this installer is somewhere inside `dir`.
+fn is_running_from(dir: &Path) -> bool {
+    let (Ok(exe), Ok(dir)) = (
+        std::env::current_exe().and_then(dunce::canonicalize),
+        dunce::canonicalize(dir),
+    ) else {
+        return false;
+    };
+    exe.starts_with(dir)
+}
+
+/// Copies this installer out of the installation and uninstalls with the copy,
+/// so the installation can be removed while the copy runs.
+async fn uninstall_from_copy(
+    options: &InstallerOptions,
+    installation_directory: &Path,
+) -> anyhow::Result<()> {
+    let path = std::env::temp_dir().join(format!(
+        "vexmason-uninstaller{}",
+        std::env::consts::EXE_SUFFIX
+    ));
+    println!(
+        "This installer is part of the installation, so it's copied to {} to uninstall.",
+        path.to_string_lossy()
+    );
+    fs::copy(std::env::current_exe()?, &path)
+        .await
+        .with_context(|| format!("failed to copy the installer to {}", path.to_string_lossy()))
+        .context(FailureKind::Filesystem)?;
+    println!("\n--- copied installer output ---\n");
+    // the copy isn't next to a recorded installation directory
+    let mut child = Command::new(&path)
+        .arg("--uninstall")
+        .args(options.subprocess_args())
+        .env(HOME_ENV_VAR, installation_directory)
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .with_context(|| "failed to spawn the copied installer")
+        .context(FailureKind::Filesystem)?;
+    let exit_status = child.wait().await?;
+    println!("\n--- finished copied installer ---");
+    // the copy can't remove itself, but it's only a temporary file
+    if !exit_status.success() {
+        let err = anyhow::anyhow!("the copied installer failed to uninstall vexmason");
+        return Err(
+            match exit_status.code().and_then(FailureKind::from_exit_code) {
+                Some(kind) => err.context(kind),
+                None => err,
+            },
+        );
+    }
+    Ok(())
+}
+
+async fn install_bin(
+    asset: &Asset,
+    filename: &str,
+    dir: &Path,
+    checksums: &HashMap<String, String>,
+) -> anyhow::Result<()> {
     println!("> downloading {}...", filename);
-    let mut response = reqwest::get(url.clone())
+    let path = dir.join(filename);
+    download_verified(asset, &path, checksums).await?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .await
+            .context(FailureKind::Filesystem)?;
+    }
+    Ok(())
+}
+
+/// Downloads `asset` to `path` and checks it against the release's checksums.
+/// On a mismatch, the download is deleted again.
+async fn download_verified(
+    asset: &Asset,
+    path: &Path,
+    checksums: &HashMap<String, String>,
+) -> anyhow::Result<()> {
+    let expected = checksums
+        .get(&asset.name)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} has no checksum for {}, refusing to install it",
+                CHECKSUMS_ASSET_NAME,
+                asset.name
+            )
+        })
+        .context(FailureKind::Verification)?;
+    let mut response = reqwest::get(asset.browser_download_url.clone())
         .await
-        .with_context(|| "failed to fetch artifact")?
+        .with_context(|| "failed to fetch artifact")
+        .context(FailureKind::Network)?
         .error_for_status()
-        .with_context(|| "failed to fetch artifact")?;
-    write_chunks(
-        &mut response,
-        &mut File::create(dir.join(filename))
-            .await
-            .with_context(|| "failed to create file to read download")?,
-    )
-    .await
-    .with_context(|| "failed to copy download content")?;
+        .with_context(|| "failed to fetch artifact")
+        .context(FailureKind::Network)?;
+    let mut file = File::create(path)
+        .await
+        .with_context(|| "failed to create file to read download")
+        .context(FailureKind::Filesystem)?;
+    let actual = write_chunks(&mut response, &mut file)
+        .await
+        .with_context(|| "failed to copy download content")
+        .context(FailureKind::Network)?;
+    if !actual.eq_ignore_ascii_case(expected) {
+        fs::remove_file(path).await?;
+        return Err(anyhow::anyhow!(
+            "checksum mismatch for {}: expected {}, got {}. the download may be corrupted or tampered with",
+            asset.name,
+            expected,
+            actual
+        )
+        .context(FailureKind::Verification));
+    }
+    println!("> verified checksum of {}", asset.name);
     Ok(())
 }
 
-async fn install_git_lib(path: &Path, git_origin: &str) -> anyhow::Result<()> {
-    let mut child = Command::new("git")
-        .args(["clone", "--depth=1", git_origin])
-        .arg(path)
-        .stdout(Stdio::null())
-        .stderr(Stdio::inherit())
-        .spawn()
-        .with_context(|| "failed to spawn git")?;
+/// Downloads and parses the `checksums.txt` asset of a release, which is in
+/// the format produced by `sha256sum`.
+async fn fetch_checksums(assets: &[Asset]) -> anyhow::Result<HashMap<String, String>> {
+    let asset = assets
+        .iter()
+        .find(|asset| asset.name == CHECKSUMS_ASSET_NAME)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "the release has no {}, so its files can't be verified",
+                CHECKSUMS_ASSET_NAME
+            )
+        })
+        .context(FailureKind::Verification)?;
+    let content = reqwest::get(asset.browser_download_url.clone())
+        .await
+        .and_then(|x| x.error_for_status())
+        .with_context(|| "failed to fetch checksums")
+        .context(FailureKind::Network)?
+        .text()
+        .await
+        .context(FailureKind::Network)?;
+    let mut checksums = HashMap::new();
+    for line in content.lines().filter(|x| !x.trim().is_empty()) {
+        let (checksum, name) = line.split_once(char::is_whitespace).ok_or_else(|| {
+            anyhow::anyhow!("malformed line in {}: {}", CHECKSUMS_ASSET_NAME, line)
+        })?;
+        // `sha256sum` marks binary mode with a `*` before the file name
+        let name = name.trim_start().trim_start_matches('*');
+        checksums.insert(name.to_string(), checksum.to_string());
+    }
+    Ok(checksums)
+}
 
-    let exit_status = child
-        .wait()
+/// Checks out `revision` of the git repository at `git_origin` into `path`,
+/// creating the repository first if needed, and records the revision and the
+/// commit it resolved to.
+async fn install_git_lib(path: &Path, git_origin: &str, revision: &str) -> anyhow::Result<()> {
+    if !path.join(".git").try_exists()? {
+        fs::create_dir_all(path).await?;
+        run_git(path, &["init", "--quiet"]).await?;
+        run_git(path, &["remote", "add", "origin", git_origin]).await?;
+    }
+    run_git(path, &["fetch", "--quiet", "--depth=1", "origin", revision]).await?;
+    run_git(
+        path,
+        &["checkout", "--quiet", "--force", "--detach", "FETCH_HEAD"],
+    )
+    .await?;
+    let commit = git_output(path, &["rev-parse", "HEAD"]).await?;
+    write_revision_file(path, revision, Some(&commit)).await
+}
+
+/// Downloads the source archive at `archive_url` (a `.tar.gz` with everything
+/// in one top-level directory, as GitHub makes them) and unpacks it into
+/// `path`, replacing what's there, and records the revision and its commit.
+async fn install_archive_lib(path: &Path, archive_url: &str, revision: &str) -> anyhow::Result<()> {
+    let archive = reqwest::get(archive_url)
         .await
-        .with_context(|| "failed to wait on child")?;
-    if exit_status.success() {
-        Ok(())
-    } else {
-        Err(anyhow::anyhow!(
-            "git clone failed with error code {}",
-            exit_status.code().unwrap_or(-1)
-        ))
+        .and_then(|x| x.error_for_status())
+        .with_context(|| format!("failed to fetch {}", archive_url))
+        .context(FailureKind::Network)?
+        .bytes()
+        .await
+        .with_context(|| format!("failed to fetch {}", archive_url))
+        .context(FailureKind::Network)?;
+
+    let staging_dir = path.with_extension("new");
+    create_staging_dir(&staging_dir)?;
+    let commit = match unpack_stripped(&archive, &staging_dir) {
+        Ok(commit) => commit,
+        Err(err) => {
+            remove_staging_dir(&staging_dir)?;
+            return Err(err.context(FailureKind::Verification));
+        }
+    };
+    write_revision_file(&staging_dir, revision, commit.as_deref()).await?;
+    replace_dir(&staging_dir, path)
+}
+
+/// Records which revision of python-compiler is in `path` and the commit it
+/// resolved to, making sure it's the commit that was asked for if `revision`
+/// is one. See [`PYTHON_COMPILER_REVISION_FILE`].
+async fn write_revision_file(
+    path: &Path,
+    revision: &str,
+    commit: Option<&str>,
+) -> anyhow::Result<()> {
+    if is_commit_hash(revision) && !commit.is_some_and(|x| x.eq_ignore_ascii_case(revision)) {
+        return Err(anyhow::anyhow!(
+            "expected python-compiler {}, but got {}",
+            revision,
+            commit.unwrap_or("an archive that doesn't record its commit")
+        )
+        .context(FailureKind::Verification));
+    }
+    fs::write(
+        path.join(PYTHON_COMPILER_REVISION_FILE),
+        format!("{}\n{}\n", revision, commit.unwrap_or_default()),
+    )
+    .await
+    .with_context(|| "failed to record the python-compiler revision")
+    .context(FailureKind::Filesystem)
+}
+
+/// Unpacks a `.tar.gz` into `destination`, leaving out its top-level directory.
+/// Returns the commit the archive was made from, which `git archive` (and so
+/// GitHub) records in a global pax header.
+fn unpack_stripped(archive: &[u8], destination: &Path) -> anyhow::Result<Option<String>> {
+    let mut commit = None;
+    let mut archive = tar::Archive::new(GzDecoder::new(archive));
+    for entry in archive
+        .entries()
+        .with_context(|| "failed to read the archive")?
+    {
+        let mut entry = entry.with_context(|| "failed to read the archive")?;
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_pax_global_extensions() {
+            if let Some(extensions) = entry.pax_extensions()? {
+                for extension in extensions {
+                    let extension = extension?;
+                    if extension.key() == Ok("comment") {
+                        commit = extension.value().ok().map(|x| x.trim().to_string());
+                    }
+                }
+            }
+            continue;
+        }
+        if !entry_type.is_file() && !entry_type.is_dir() {
+            continue;
+        }
+        let relative: PathBuf = entry.path()?.components().skip(1).collect();
+        if relative
+            .components()
+            .any(|x| !matches!(x, std::path::Component::Normal(_)))
+        {
+            bail!(
+                "the archive contains an invalid path {}",
+                relative.to_string_lossy()
+            );
+        }
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+        let target = destination.join(relative);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        entry
+            .unpack(&target)
+            .with_context(|| format!("failed to unpack {}", target.to_string_lossy()))?;
     }
+    Ok(commit)
 }
 
-async fn pull_git_lib(path: &Path) -> anyhow::Result<()> {
+/// Runs git in `path` and returns what it printed, trimmed.
+async fn git_output(path: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .stderr(Stdio::inherit())
+        .current_dir(path)
+        .output()
+        .await
+        .with_context(|| "failed to spawn git")?;
+    ensure!(
+        output.status.success(),
+        "git {} failed with error code {}",
+        args.first().unwrap_or(&""),
+        output.status.code().unwrap_or(-1)
+    );
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+async fn run_git(path: &Path, args: &[&str]) -> anyhow::Result<()> {
     let mut child = Command::new("git")
-        .arg("pull")
+        .args(args)
         .stdout(Stdio::null())
         .stderr(Stdio::inherit())
         .current_dir(path)
@@ -316,17 +1131,21 @@ async fn pull_git_lib(path: &Path) -> anyhow::Result<()> {
         Ok(())
     } else {
         Err(anyhow::anyhow!(
-            "git clone failed with error code {}",
+            "git {} failed with error code {}",
+            args.first().unwrap_or(&""),
             exit_status.code().unwrap_or(-1)
         ))
     }
 }
 
-async fn write_chunks(response: &mut Response, file: &mut fs::File) -> anyhow::Result<()> {
+/// Writes the response body to `file` and returns its SHA-256 as hex.
+async fn write_chunks(response: &mut Response, file: &mut fs::File) -> anyhow::Result<String> {
     let mut writer = BufWriter::new(file);
+    let mut hasher = Sha256::new();
     while let Some(chunk) = response.chunk().await? {
+        hasher.update(&chunk);
         writer.write_all(&chunk).await?;
     }
     writer.flush().await?;
-    Ok(())
+    Ok(format!("{:x}", hasher.finalize()))
 }
@@ -0,0 +1,48 @@
+use anyhow::bail;
+
+/// A minimal parser for `--flag value`, `--flag=value` and positional
+/// arguments.
+pub struct Args {
+    args: std::vec::IntoIter<String>,
+}
+
+pub enum Arg {
+    Flag(String),
+    Positional(String),
+}
+
+impl Args {
+    pub fn new(args: Vec<String>) -> Self {
+        Self {
+            args: args.into_iter(),
+        }
+    }
+
+    /// Gets the next argument. A `--flag=value` argument is split so that the
+    /// value is returned by the following call to [`Args::value`].
+    pub fn next_arg(&mut self) -> Option<Arg> {
+        let arg = self.args.next()?;
+        if arg.starts_with("--") {
+            if let Some((flag, value)) = arg.split_once('=') {
+                let rest: Vec<String> = std::iter::once(value.to_string())
+                    .chain(self.args.by_ref())
+                    .collect();
+                self.args = rest.into_iter();
+                return Some(Arg::Flag(flag.to_string()));
+            }
+            Some(Arg::Flag(arg))
+        } else if arg.starts_with('-') && arg.len() > 1 {
+            Some(Arg::Flag(arg))
+        } else {
+            Some(Arg::Positional(arg))
+        }
+    }
+
+    /// Gets the value for `flag`.
+    pub fn value(&mut self, flag: &str) -> anyhow::Result<String> {
+        match self.args.next() {
+            Some(value) => Ok(value),
+            None => bail!("{} requires a value", flag),
+        }
+    }
+}
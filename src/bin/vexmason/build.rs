@@ -0,0 +1,183 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{bail, Context};
+use log::{debug, error, info, warn};
+use tokio::fs;
+use vexmason::{
+    build_cache, compile_file,
+    config::{
+        self, parse_define, resolved_config_from_root, root, CliOverrides, ResolvedConfig,
+        CONFIG_FILE, CONFIG_OVERRIDES_FILE,
+    },
+    hook::unhooked_extensions,
+    installation_location,
+    python::find_python,
+};
+
+use crate::{
+    args::{Arg, Args},
+    start_logger,
+};
+
+/// Finds the project root from `path`, with an error message that tells the
+/// user what's missing.
+pub fn find_root(path: &Path) -> anyhow::Result<PathBuf> {
+    root(path).with_context(|| {
+        anyhow::anyhow!(
+            "failed to find your project! Make sure it has a vex_project_settings.json and a {} present inside the .vscode directory.",
+            config::CONFIG_FILE
+        )
+    })
+}
+
+/// Creates the build directory if it doesn't exist yet and returns it.
+pub async fn create_build_dir(root: &Path) -> anyhow::Result<PathBuf> {
+    let build_dir = root.join("build");
+    if !build_dir.exists() {
+        fs::create_dir(&build_dir)
+            .await
+            .with_context(|| "failed create the build output directory")?;
+    }
+    Ok(build_dir)
+}
+
+/// Warns about VEXcode extensions that aren't hooked yet, since VS Code
+/// installs updates side by side and uploads through a new version skip
+/// vexmason. It only warns so that builds never change the extensions.
+async fn warn_unhooked_extensions() {
+    let result: anyhow::Result<Vec<PathBuf>> = async {
+        let extensions_dir = installation_location::get_vscode_extensions_directory(
+            env::current_exe().ok().as_deref(),
+        )?;
+        if !extensions_dir.is_dir() {
+            debug!("no VS Code extensions directory, so not checking for unhooked extensions");
+            return Ok(Vec::new());
+        }
+        unhooked_extensions(&extensions_dir).await
+    }
+    .await;
+    match result {
+        Ok(unhooked) => {
+            for extension in unhooked {
+                warn!(
+                    "uploads through {:?} skip vexmason because it isn't hooked. run `vexmason rehook` to hook it",
+                    extension.file_name().unwrap_or_default()
+                );
+            }
+        }
+        Err(err) => warn!("failed to check for unhooked VEXcode extensions: {:#}", err),
+    }
+}
+
+/// Resolves the config and logs it.
+pub async fn resolve_config(
+    root: &Path,
+    cli_overrides: &CliOverrides,
+) -> anyhow::Result<ResolvedConfig> {
+    let config = resolved_config_from_root(root, cli_overrides)
+        .await
+        .with_context(|| "couldn't resolve config")?;
+
+    info!(
+        "resolved config from {} and {}",
+        CONFIG_FILE, CONFIG_OVERRIDES_FILE
+    );
+    debug!("{:#?}", config);
+    Ok(config)
+}
+
+/// Compiles the project into its build output, unless the build cache says
+/// nothing has changed since the last build.
+pub async fn build_project(config: &ResolvedConfig, user_directory: &Path) -> anyhow::Result<()> {
+    let build_output = config.build_output();
+    let build_dir = config.project_root.join("build");
+    let python = find_python(config.python.as_deref(), Some(&config.project_root))
+        .with_context(|| "failed to find a Python interpreter")?;
+    let options = compile_file::CompileFileOptions {
+        input: &config.entry_file,
+        output: Some(&build_output),
+        minify: config.minify,
+        defines: &config.defines,
+        app_data_location: &user_directory.join("AppData").join("Roaming"),
+        project_root: &config.project_root,
+        python: &python,
+        timeout: config.compile_timeout,
+        remove_imports: &config.remove_imports,
+        prelude: &config.prelude,
+        export_dictionary_mode: &config.export_dictionary_mode,
+    };
+    let hash = build_cache::build_hash(&options)
+        .await
+        .with_context(|| "failed to hash build inputs")?;
+    if build_cache::is_cached(&build_dir, &build_output, &hash).await {
+        info!("build cache hit ({}), skipping compile step", hash);
+    } else {
+        info!("build cache miss ({}), compiling", hash);
+        build_cache::clear_cache(&build_dir).await?;
+        compile_file::compile_file(&options)
+            .await
+            .with_context(|| "failed to compile file")?;
+        build_cache::write_cache(&build_dir, &hash).await?;
+    }
+    Ok(())
+}
+
+/// `vexmason build`: compiles a project without going through vexcom.
+pub async fn build_command(args: Vec<String>) -> anyhow::Result<ExitCode> {
+    let mut project = None;
+    let mut cli_overrides = CliOverrides::default();
+    let mut args = Args::new(args);
+    while let Some(arg) = args.next_arg() {
+        match arg {
+            Arg::Flag(flag) if flag == "--project" => {
+                project = Some(PathBuf::from(args.value(&flag)?));
+            }
+            Arg::Flag(flag) if flag == "--profile" => {
+                cli_overrides.profile = Some(args.value(&flag)?);
+            }
+            Arg::Flag(flag) if flag == "--define" || flag == "-D" => {
+                cli_overrides
+                    .defines
+                    .push(parse_define(&args.value(&flag)?)?);
+            }
+            Arg::Flag(flag) => bail!("unknown option '{}' for `vexmason build`", flag),
+            Arg::Positional(arg) => bail!("unexpected argument '{}' for `vexmason build`", arg),
+        }
+    }
+
+    let project = match project {
+        Some(project) => dunce::canonicalize(&project).with_context(|| {
+            format!(
+                "failed to find project directory {}",
+                project.to_string_lossy()
+            )
+        })?,
+        None => env::current_dir()?,
+    };
+    let root = find_root(&project)?;
+    let build_dir = create_build_dir(&root).await?;
+    let _logger = start_logger(&build_dir, true)?;
+    info!("started log");
+    warn_unhooked_extensions().await;
+
+    let result: anyhow::Result<()> = async {
+        let user_directory = installation_location::get_user_directory(None)?;
+        let config = resolve_config(&root, &cli_overrides).await?;
+        build_project(&config, &user_directory).await?;
+        info!("wrote {}", config.build_output().to_string_lossy());
+        Ok(())
+    }
+    .await;
+    match result {
+        Ok(()) => Ok(ExitCode::SUCCESS),
+        Err(err) => {
+            // the log is duplicated to stderr, so this is what the user sees
+            error!("{:#}", err);
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
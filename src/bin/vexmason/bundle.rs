@@ -0,0 +1,52 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use anyhow::{bail, Context};
+use vexmason::{
+    bundle::create_bundle,
+    hook::find_shim,
+    installation_location::{get_installation_path, get_vscode_extensions_directory, VEXCOM_NAME},
+};
+
+use crate::args::{Arg, Args};
+
+/// `vexmason bundle`: packs the current installation into an archive that
+/// `vexmason-installer --offline-bundle` can install from without internet.
+pub async fn bundle_command(args: Vec<String>) -> anyhow::Result<ExitCode> {
+    let mut output = None;
+    let mut args = Args::new(args);
+    while let Some(arg) = args.next_arg() {
+        match arg {
+            Arg::Flag(flag) if flag == "--output" || flag == "-o" => {
+                output = Some(PathBuf::from(args.value(&flag)?));
+            }
+            Arg::Flag(flag) => bail!("unknown option '{}' for `vexmason bundle`", flag),
+            Arg::Positional(arg) => bail!("unexpected argument '{}' for `vexmason bundle`", arg),
+        }
+    }
+    let output = output.unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "vexmason-bundle-v{}.tar.gz",
+            env!("CARGO_PKG_VERSION")
+        ))
+    });
+
+    let current_exe = std::env::current_exe().ok();
+    let installation_directory = get_installation_path(current_exe.as_deref())?;
+    let extensions_dir = get_vscode_extensions_directory(current_exe.as_deref())?;
+    let vexcom_shim = find_shim(&installation_directory, &extensions_dir).await?;
+
+    println!(
+        "bundling {} and {}...",
+        installation_directory.to_string_lossy(),
+        vexcom_shim.to_string_lossy()
+    );
+    let manifest = create_bundle(&installation_directory, &vexcom_shim, VEXCOM_NAME, &output)
+        .with_context(|| "failed to create the bundle")?;
+    println!(
+        "wrote {} ({} files). install it with `vexmason-installer --offline-bundle {}`",
+        output.to_string_lossy(),
+        manifest.checksums.len(),
+        output.to_string_lossy()
+    );
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,89 @@
+use std::{env, path::PathBuf, process::ExitCode};
+
+use anyhow::{bail, Context};
+use vexmason::config::{parse_define, resolved_config_from_root, CliOverrides};
+
+use crate::{
+    args::{Arg, Args},
+    build::find_root,
+};
+
+/// `vexmason defines`: lists the project's defines with their current values
+/// and metadata.
+pub async fn defines_command(args: Vec<String>) -> anyhow::Result<ExitCode> {
+    let mut project = None;
+    let mut json = false;
+    let mut cli_overrides = CliOverrides::default();
+    let mut args = Args::new(args);
+    while let Some(arg) = args.next_arg() {
+        match arg {
+            Arg::Flag(flag) if flag == "--project" => {
+                project = Some(PathBuf::from(args.value(&flag)?));
+            }
+            Arg::Flag(flag) if flag == "--profile" => {
+                cli_overrides.profile = Some(args.value(&flag)?);
+            }
+            Arg::Flag(flag) if flag == "--define" || flag == "-D" => {
+                cli_overrides
+                    .defines
+                    .push(parse_define(&args.value(&flag)?)?);
+            }
+            Arg::Flag(flag) if flag == "--json" => json = true,
+            Arg::Flag(flag) => bail!("unknown option '{}' for `vexmason defines`", flag),
+            Arg::Positional(arg) => bail!("unexpected argument '{}' for `vexmason defines`", arg),
+        }
+    }
+
+    let root = find_root(&match project {
+        Some(project) => project,
+        None => env::current_dir()?,
+    })?;
+    let config = resolved_config_from_root(&root, &cli_overrides)
+        .await
+        .with_context(|| "couldn't resolve config")?;
+    let infos = config.define_infos();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&infos)?);
+        return Ok(ExitCode::SUCCESS);
+    }
+    if infos.is_empty() {
+        println!("no defines in `default_defines`");
+    }
+    let mut group = None;
+    for info in infos {
+        if info.group != group {
+            group = info.group.clone();
+            if let Some(group) = &group {
+                println!("[{}]", group);
+            }
+        }
+        let label = info
+            .options
+            .iter()
+            .flatten()
+            .find(|x| x.value == info.value)
+            .and_then(|x| x.label.as_deref());
+        match label {
+            Some(label) => println!(
+                "{} = {} ({}, from {})",
+                info.name, info.value, label, info.source
+            ),
+            None => println!("{} = {} (from {})", info.name, info.value, info.source),
+        }
+        if let Some(description) = &info.description {
+            println!("    {}", description);
+        }
+        if let Some(options) = &info.options {
+            let options = options
+                .iter()
+                .map(|x| match &x.label {
+                    Some(label) => format!("{} ({})", x.value, label),
+                    None => x.value.to_string(),
+                })
+                .collect::<Vec<_>>();
+            println!("    options: {}", options.join(", "));
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,54 @@
+use std::{env, process::ExitCode};
+
+use anyhow::bail;
+use vexmason::doctor::{run_checks, CheckStatus};
+
+use crate::args::{Arg, Args};
+
+/// `vexmason doctor`: checks the environment and the current project for
+/// common problems.
+pub async fn doctor_command(args: Vec<String>) -> anyhow::Result<ExitCode> {
+    let mut json = false;
+    let mut args = Args::new(args);
+    while let Some(arg) = args.next_arg() {
+        match arg {
+            Arg::Flag(flag) if flag == "--json" => json = true,
+            Arg::Flag(flag) => bail!("unknown option '{}' for `vexmason doctor`", flag),
+            Arg::Positional(arg) => bail!("unexpected argument '{}' for `vexmason doctor`", arg),
+        }
+    }
+
+    let checks = run_checks(&env::current_dir()?).await;
+    let failed = checks.iter().any(|x| x.status == CheckStatus::Fail);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&checks)?);
+    } else {
+        for check in &checks {
+            let status = match check.status {
+                CheckStatus::Pass => "PASS",
+                CheckStatus::Warn => "WARN",
+                CheckStatus::Fail => "FAIL",
+                CheckStatus::Skip => "SKIP",
+            };
+            println!("[{}] {}: {}", status, check.name, check.message);
+            if check.status != CheckStatus::Pass {
+                if let Some(suggestion) = &check.suggestion {
+                    println!("       fix: {}", suggestion);
+                }
+            }
+        }
+        println!();
+        if failed {
+            println!("some checks failed. see the suggestions above to fix them.");
+        } else {
+            println!("everything looks good!");
+        }
+    }
+
+    Ok(if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
@@ -0,0 +1,48 @@
+use std::{env, path::PathBuf, process::ExitCode};
+
+use anyhow::{bail, Context};
+use vexmason::init::{init_project, InitAction, InitOptions};
+
+use crate::args::{Arg, Args};
+
+/// `vexmason init`: creates a new project, or converts an existing VEXcode
+/// Python project in place.
+pub async fn init_command(args: Vec<String>) -> anyhow::Result<ExitCode> {
+    let mut directory = None;
+    let mut name = None;
+    let mut args = Args::new(args);
+    while let Some(arg) = args.next_arg() {
+        match arg {
+            Arg::Flag(flag) if flag == "--name" => name = Some(args.value(&flag)?),
+            Arg::Flag(flag) => bail!("unknown option '{}' for `vexmason init`", flag),
+            Arg::Positional(arg) if directory.is_none() => directory = Some(PathBuf::from(arg)),
+            Arg::Positional(arg) => bail!("unexpected argument '{}' for `vexmason init`", arg),
+        }
+    }
+
+    let root = match directory {
+        Some(directory) => {
+            tokio::fs::create_dir_all(&directory)
+                .await
+                .with_context(|| "failed to create project directory")?;
+            directory
+        }
+        None => env::current_dir()?,
+    };
+    let actions = init_project(&InitOptions {
+        root: &root,
+        name: name.as_deref(),
+    })
+    .await?;
+    for action in actions {
+        match action {
+            InitAction::Created(path) => println!("created {}", path.to_string_lossy()),
+            InitAction::Updated(path) => println!("updated {}", path.to_string_lossy()),
+            InitAction::Skipped { path, reason } => {
+                println!("skipped {} ({})", path.to_string_lossy(), reason)
+            }
+        }
+    }
+    println!("your vexmason project is ready. run `vexmason build` to try it out.");
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,120 @@
+mod args;
+mod build;
+mod bundle;
+mod defines;
+mod doctor;
+mod init;
+mod rehook;
+mod shim;
+mod traceback;
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use flexi_logger::{Duplicate, FileSpec, LogSpecification, Logger, LoggerHandle};
+
+const USAGE: &str = "usage: vexmason <command> [options]
+
+commands:
+  build [--project DIR] [--profile NAME] [--define KEY=VALUE]...
+      compile the project into build/compiled.py
+  bundle [--output FILE]
+      pack this installation into an archive for offline installs with
+      `vexmason-installer --offline-bundle FILE`
+  defines [--project DIR] [--profile NAME] [--define KEY=VALUE]... [--json]
+      list the project's defines with their current values and descriptions
+  doctor [--json]
+      check the installation and the current project for common problems
+  init [DIR] [--name NAME]
+      create a new project, or convert an existing VEXcode python project
+  rehook
+      install the vexcom shim into VEXcode extensions that were installed or
+      updated after vexmason
+  traceback [FILE]
+      rewrite a traceback from the brain (read from FILE or stdin) to point at
+      the original source files
+  help
+      show this message
+
+when run by the vexcom shim, the first argument is the path to vexcom instead
+of a command.
+";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match runtime().await {
+        Ok(code) => code,
+        Err(err) => {
+            let mut err_str = format!("{:#}", err);
+            if let Some(stripped) = err_str.strip_prefix("Error: ") {
+                err_str = stripped.to_string();
+            }
+            eprintln!("{err_str}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn runtime() -> Result<ExitCode, anyhow::Error> {
+    let mut args = env::args().skip(1);
+    let first_arg = args.next().ok_or(anyhow::anyhow!(
+        "no command given. run `vexmason help` for usage"
+    ))?;
+    match first_arg.as_str() {
+        "build" => build::build_command(args.collect()).await,
+        "bundle" => bundle::bundle_command(args.collect()).await,
+        "defines" => defines::defines_command(args.collect()).await,
+        "doctor" => doctor::doctor_command(args.collect()).await,
+        "init" => init::init_command(args.collect()).await,
+        "rehook" => rehook::rehook_command(args.collect()).await,
+        "traceback" => traceback::traceback_command(args.collect()).await,
+        "help" | "--help" | "-h" => {
+            print!("{USAGE}");
+            Ok(ExitCode::SUCCESS)
+        }
+        "--version" | "-V" => {
+            println!("vexmason {}", env!("CARGO_PKG_VERSION"));
+            Ok(ExitCode::SUCCESS)
+        }
+        // the vexcom shim always passes an absolute path first
+        _ if Path::new(&first_arg).is_absolute() => {
+            shim::shim_runtime(PathBuf::from(first_arg), args.collect()).await
+        }
+        _ => Err(anyhow::anyhow!(
+            "unknown command '{}'. run `vexmason help` for usage",
+            first_arg
+        )),
+    }
+}
+
+/// Starts logging to `vexmason.log` in the build directory. The returned
+/// handle must be kept alive until the program exits.
+fn start_logger(build_dir: &Path, duplicate_to_stderr: bool) -> anyhow::Result<LoggerHandle> {
+    let logger = Logger::with(LogSpecification::trace())
+        .adaptive_format_for_stderr(flexi_logger::AdaptiveFormat::Default)
+        .log_to_file(
+            FileSpec::default()
+                .directory(build_dir)
+                .basename("vexmason")
+                .suppress_timestamp(),
+        )
+        .format_for_files(|w, now, record| {
+            write!(
+                w,
+                "[{}] {:<5} [{}] {}",
+                now.format("%Y-%m-%d %H:%M:%S"),
+                record.level(),
+                record.module_path().unwrap_or("<unnamed>"),
+                &record.args()
+            )
+        });
+    let logger = if duplicate_to_stderr || cfg!(feature = "stderr_log") {
+        logger.duplicate_to_stderr(Duplicate::Info)
+    } else {
+        logger
+    };
+    Ok(logger.start()?)
+}
@@ -0,0 +1,37 @@
+use std::process::ExitCode;
+
+use anyhow::bail;
+use vexmason::{
+    hook::{find_shim, hook_all},
+    installation_location::{get_installation_path, get_vscode_extensions_directory},
+};
+
+use crate::args::{Arg, Args};
+
+/// `vexmason rehook`: installs the vexcom shim into VEXcode extensions that
+/// VS Code installed after vexmason, e.g. when the extension updates.
+pub async fn rehook_command(args: Vec<String>) -> anyhow::Result<ExitCode> {
+    let mut args = Args::new(args);
+    if let Some(arg) = args.next_arg() {
+        match arg {
+            Arg::Flag(flag) => bail!("unknown option '{}' for `vexmason rehook`", flag),
+            Arg::Positional(arg) => bail!("unexpected argument '{}' for `vexmason rehook`", arg),
+        }
+    }
+
+    let current_exe = std::env::current_exe().ok();
+    let installation_directory = get_installation_path(current_exe.as_deref())?;
+    let extensions_dir = get_vscode_extensions_directory(current_exe.as_deref())?;
+    let shim = find_shim(&installation_directory, &extensions_dir).await?;
+    let hooked = hook_all(&extensions_dir, &shim).await?;
+    if hooked.is_empty() {
+        println!("every VEXcode extension is already hooked");
+    }
+    for extension in hooked {
+        println!(
+            "hooked {}",
+            extension.file_name().unwrap_or_default().to_string_lossy()
+        );
+    }
+    Ok(ExitCode::SUCCESS)
+}
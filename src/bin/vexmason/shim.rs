@@ -1,47 +1,30 @@
+//! The mode used when vexmason is launched by the vexcom shim from the VEX
+//! extension.
 use std::{
-    env,
     path::{Path, PathBuf},
     process::{ExitCode, Stdio},
 };
 
 use anyhow::Context;
 
-use flexi_logger::{FileSpec, LogSpecification, Logger};
-use log::{debug, error, info};
-use tokio::{fs, io::stderr, process::Command};
+use log::{debug, error, info, warn};
+use tokio::{io::stderr, process::Command};
 use vexmason::{
-    compile_file,
-    config::{self, resolved_config_from_root, root, CONFIG_FILE, CONFIG_OVERRIDES_FILE},
+    config::CliOverrides,
+    hook::hook_all,
     installation_location::{self, VEXCOM_OLD_NAME},
     modify_args::{entry_point, has_write, modify_args, ModifyOptions},
     save_readable::save_readable,
 };
 
-#[tokio::main]
-async fn main() -> ExitCode {
-    match runtime().await {
-        Ok(code) => code,
-        Err(err) => {
-            let mut err_str = format!("{}", err);
-            if let Some(stripped) = err_str.strip_prefix("Error: ") {
-                err_str = stripped.to_string();
-            }
-            eprintln!("{err_str}");
-            ExitCode::FAILURE
-        }
-    }
-}
+use crate::{
+    build::{build_project, create_build_dir, find_root, resolve_config},
+    start_logger,
+};
 
-async fn runtime() -> Result<ExitCode, anyhow::Error> {
-    let mut args = env::args().skip(1);
-    let vexcom_location = Into::<PathBuf>::into(
-        args.next()
-            .ok_or(anyhow::anyhow!("can't read vexcom location"))?,
-    );
+pub async fn shim_runtime(vexcom_location: PathBuf, args: Vec<String>) -> anyhow::Result<ExitCode> {
     let user_directory = installation_location::get_user_directory(Some(&vexcom_location))?;
 
-    let args: Vec<String> = args.collect();
-
     match entry_point(&args) {
         None => {
             // if not in a vexmason project, just proxy to vexcom
@@ -77,45 +60,13 @@ async fn runtime() -> Result<ExitCode, anyhow::Error> {
             }
         }
         Some(entry_point) => {
-            let root = root(entry_point).with_context(
-                || anyhow::anyhow!(
-                    "failed to find your project! Make sure it has a vex_project_settings.json and a {} present inside the .vscode directory.",
-                    config::CONFIG_FILE
-                )
-            )?;
-            let build_dir = root.join("build");
-            if !build_dir.exists() {
-                fs::create_dir(root.join("build"))
-                    .await
-                    .with_context(|| "failed create the build output directory")?;
-            }
-
-            let logger = Logger::with(LogSpecification::trace())
-                .adaptive_format_for_stderr(flexi_logger::AdaptiveFormat::Default)
-                .log_to_file(
-                    FileSpec::default()
-                        .directory(build_dir)
-                        .basename("vexmason")
-                        .suppress_timestamp(),
-                )
-                .format_for_files(|w, now, record| {
-                    write!(
-                        w,
-                        "[{}] {:<5} [{}] {}",
-                        now.format("%Y-%m-%d %H:%M:%S"),
-                        record.level(),
-                        record.module_path().unwrap_or("<unnamed>"),
-                        &record.args()
-                    )
-                });
-            #[cfg(feature = "stderr_log")]
-            {
-                logger.log_to_stderr();
-            }
-            logger.start()?;
+            let root = find_root(entry_point)?;
+            let build_dir = create_build_dir(&root).await?;
+            let _logger = start_logger(&build_dir, false)?;
             info!("started log");
 
             debug!("vexcom location: {:?}", vexcom_location);
+            hook_new_extensions(&vexcom_location).await;
 
             match compiling_runtime(args, &root, &user_directory, &vexcom_location).await {
                 Err(err) => {
@@ -131,6 +82,25 @@ async fn runtime() -> Result<ExitCode, anyhow::Error> {
     }
 }
 
+/// Installs the shim (which is `vexcom_location`) into any VEXcode extensions
+/// that aren't hooked yet, since VS Code installs updates side by side.
+async fn hook_new_extensions(vexcom_location: &Path) {
+    let result: anyhow::Result<Vec<PathBuf>> = async {
+        let extensions_dir =
+            installation_location::get_vscode_extensions_directory(Some(vexcom_location))?;
+        hook_all(&extensions_dir, vexcom_location).await
+    }
+    .await;
+    match result {
+        Ok(hooked) => {
+            for extension in hooked {
+                info!("installed the vexcom shim into {:?}", extension);
+            }
+        }
+        Err(err) => warn!("failed to check for unhooked VEXcode extensions: {:#}", err),
+    }
+}
+
 async fn compiling_runtime(
     mut args: Vec<String>,
     root: &Path,
@@ -139,26 +109,10 @@ async fn compiling_runtime(
 ) -> anyhow::Result<Option<u8>> {
     debug!("vexmason command-line arguments => {:?}", args);
 
-    let config = resolved_config_from_root(&root)
-        .await
-        .with_context(|| "couldn't resolve config")?;
-
-    info!(
-        "resolved config from {} and {}",
-        CONFIG_FILE, CONFIG_OVERRIDES_FILE
-    );
-    info!("{:#?}", config);
+    let config = resolve_config(root, &CliOverrides::default()).await?;
 
     if has_write(&args) {
-        compile_file::compile_file(&compile_file::CompileFileOptions {
-            input: &config.entry_file,
-            output: Some(&config.build_output()),
-            minify: config.minify,
-            defines: &config.defines,
-            app_data_location: &user_directory.join("AppData").join("Roaming"),
-        })
-        .await
-        .with_context(|| "failed to compile file")?;
+        build_project(&config, user_directory).await?;
     } else {
         info!("no --write argument supplied, so skipping compile step");
     }
@@ -209,8 +163,7 @@ async fn compiling_runtime(
         error!("vexcom exited with a non-zero exit code");
         error!(
             "vexcom stderr:\n{}",
-            std::str::from_utf8(&child_stderr)
-                .unwrap_or_else(|_| "failed to decode vexcom stderr to print")
+            std::str::from_utf8(&child_stderr).unwrap_or("failed to decode vexcom stderr to print")
         );
         Ok(Some(
             child_exit_status
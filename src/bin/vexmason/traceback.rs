@@ -0,0 +1,52 @@
+use std::{env, process::ExitCode};
+
+use anyhow::{bail, Context};
+use tokio::{fs, io::AsyncReadExt};
+use vexmason::{
+    config::{resolved_config_from_root, CliOverrides},
+    source_map::{source_map_path, SourceMap},
+};
+
+use crate::{
+    args::{Arg, Args},
+    build::find_root,
+};
+
+/// `vexmason traceback`: rewrites a traceback from the brain (read from a file
+/// or stdin) to point at the original source files.
+pub async fn traceback_command(args: Vec<String>) -> anyhow::Result<ExitCode> {
+    let mut path = None;
+    let mut args = Args::new(args);
+    while let Some(arg) = args.next_arg() {
+        match arg {
+            Arg::Positional(arg) if path.is_none() => path = Some(arg),
+            Arg::Positional(arg) => {
+                bail!("unexpected argument '{}' for `vexmason traceback`", arg)
+            }
+            Arg::Flag(flag) => bail!("unknown option '{}' for `vexmason traceback`", flag),
+        }
+    }
+
+    let traceback = match path {
+        Some(path) => fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("failed to read traceback from {}", path))?,
+        None => {
+            let mut traceback = String::new();
+            tokio::io::stdin()
+                .read_to_string(&mut traceback)
+                .await
+                .with_context(|| "failed to read traceback from stdin")?;
+            traceback
+        }
+    };
+    let root = find_root(&env::current_dir()?)?;
+    let config = resolved_config_from_root(&root, &CliOverrides::default())
+        .await
+        .with_context(|| "couldn't resolve config")?;
+    let source_map = SourceMap::read(&source_map_path(&config.build_output()))
+        .await
+        .with_context(|| "couldn't read the source map. try building your project again.")?;
+    print!("{}", source_map.rewrite_traceback(&traceback));
+    Ok(ExitCode::SUCCESS)
+}
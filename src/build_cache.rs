@@ -0,0 +1,142 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::{fs, io};
+
+use crate::{
+    compile_file::{lib_dir, CompileFileOptions},
+    modules::reachable_modules,
+    source_map::source_map_path,
+};
+
+pub const CACHE_FILE: &str = "vexmason-cache.json";
+
+/// What's stored in `build/` after each successful compile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    hash: String,
+}
+
+pub fn cache_path(build_dir: &Path) -> PathBuf {
+    build_dir.join(CACHE_FILE)
+}
+
+/// Hashes everything that can affect the output of
+/// [`compile_file`](crate::compile_file::compile_file): every module reachable
+/// from the entry file, the defines, the options, the Python interpreter and
+/// the installed compiler.
+pub async fn build_hash(options: &CompileFileOptions<'_>) -> anyhow::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut update = |field: &[u8]| {
+        // length-prefix every field so adjacent fields can't be confused
+        hasher.update((field.len() as u64).to_le_bytes());
+        hasher.update(field);
+    };
+
+    update(env!("CARGO_PKG_VERSION").as_bytes());
+    update(options.python.to_string().as_bytes());
+    update(options.python.version.to_string().as_bytes());
+    for file in compiler_files(&lib_dir()?.join("python-compiler")).await? {
+        update(file.to_string_lossy().as_bytes());
+        update(&fs::read(&file).await?);
+    }
+
+    update(options.input.to_string_lossy().as_bytes());
+    for module in reachable_modules(options.input).await? {
+        update(module.to_string_lossy().as_bytes());
+        update(&fs::read(&module).await?);
+    }
+
+    let mut defines: Vec<_> = options.defines.iter().collect();
+    defines.sort_by(|a, b| a.0.cmp(b.0));
+    for (k, v) in defines {
+        update(k.as_bytes());
+        // includes the type, so "1" and 1 hash differently
+        update(serde_json::to_string(v)?.as_bytes());
+    }
+
+    update(&[options.minify as u8]);
+    update(&(options.remove_imports.len() as u64).to_le_bytes());
+    for module in options.remove_imports {
+        update(module.as_bytes());
+    }
+    update(options.prelude.as_bytes());
+    update(options.export_dictionary_mode.as_bytes());
+    update(
+        options
+            .output
+            .map(|x| x.to_string_lossy())
+            .unwrap_or_default()
+            .as_bytes(),
+    );
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Lists the python sources of the compiler, in a stable order.
+async fn compiler_files(compiler_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![compiler_dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = match fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            // a missing compiler will fail the compile step with a better error
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => Err(e).with_context(|| "failed to read python-compiler directory")?,
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let name = entry.file_name();
+            if entry.file_type().await?.is_dir() {
+                if name != ".git" && name != "__pycache__" {
+                    dirs.push(path);
+                }
+            } else if path.extension().is_some_and(|x| x == "py") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Checks whether the previous build in `build_dir` had the same hash and its
+/// output is still there.
+pub async fn is_cached(build_dir: &Path, output: &Path, hash: &str) -> bool {
+    if !output.is_file() || !source_map_path(output).is_file() {
+        return false;
+    }
+    let Ok(content) = fs::read_to_string(cache_path(build_dir)).await else {
+        return false;
+    };
+    match serde_json::from_str::<CacheEntry>(&content) {
+        Ok(entry) => entry.hash == hash,
+        Err(e) => {
+            debug!("ignoring unreadable build cache: {}", e);
+            false
+        }
+    }
+}
+
+pub async fn write_cache(build_dir: &Path, hash: &str) -> anyhow::Result<()> {
+    let entry = CacheEntry {
+        hash: hash.to_string(),
+    };
+    fs::write(cache_path(build_dir), serde_json::to_string(&entry)?)
+        .await
+        .with_context(|| "failed to write build cache")
+}
+
+/// Removes the cache entry so the next build can't be a cache hit. Used
+/// before compiling so that a failed compile never leaves a stale entry.
+pub async fn clear_cache(build_dir: &Path) -> anyhow::Result<()> {
+    match fs::remove_file(cache_path(build_dir)).await {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            Err(e).with_context(|| "failed to clear build cache")
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::installation_location::HOME_FILE_NAME;
+
+/// The file listing the contents of a bundle, stored at its root.
+pub const BUNDLE_MANIFEST_FILE: &str = "vexmason-bundle.json";
+
+/// The directory inside a bundle holding the vexcom shim.
+pub const BUNDLE_VEXCOM_DIR: &str = "vexcom";
+
+/// The directories of an installation that are put into a bundle.
+const BUNDLED_DIRECTORIES: [&str; 2] = ["bin", "lib/python-compiler"];
+
+/// Describes an offline installation bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    /// the version of vexmason the bundle was created from
+    pub version: String,
+    /// the SHA-256 of every file in the bundle, keyed by its path inside the
+    /// bundle with `/` separators
+    pub checksums: BTreeMap<String, String>,
+}
+
+/// Packs the binaries and python-compiler of the installation in
+/// `installation_directory` and the vexcom shim into a `.tar.gz` at `output`,
+/// so vexmason can be installed without internet access.
+pub fn create_bundle(
+    installation_directory: &Path,
+    vexcom_shim: &Path,
+    vexcom_name: &str,
+    output: &Path,
+) -> anyhow::Result<BundleManifest> {
+    let mut files = Vec::new();
+    for directory in BUNDLED_DIRECTORIES {
+        let path = installation_directory.join(directory);
+        if !path.is_dir() {
+            bail!(
+                "{} doesn't exist, so the installation is incomplete",
+                path.to_string_lossy()
+            );
+        }
+        // the installation directory recorded for the shim is only right on
+        // this computer
+        files.extend(
+            list_files(&path, directory)?
+                .into_iter()
+                .filter(|(_, path)| path.file_name() != Some(HOME_FILE_NAME.as_ref())),
+        );
+    }
+    files.push((
+        format!("{}/{}", BUNDLE_VEXCOM_DIR, vexcom_name),
+        vexcom_shim.to_path_buf(),
+    ));
+
+    let mut manifest = BundleManifest {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        checksums: BTreeMap::new(),
+    };
+    let output_file = File::create(output)
+        .with_context(|| format!("failed to create {}", output.to_string_lossy()))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(output_file, Compression::default()));
+    for (name, path) in files {
+        manifest.checksums.insert(name.clone(), hash_file(&path)?);
+        builder
+            .append_path_with_name(&path, &name)
+            .with_context(|| format!("failed to add {} to the bundle", path.to_string_lossy()))?;
+    }
+
+    let manifest_content = serde_json::to_vec_pretty(&manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest_content.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    builder.append_data(
+        &mut header,
+        BUNDLE_MANIFEST_FILE,
+        manifest_content.as_slice(),
+    )?;
+    builder
+        .into_inner()?
+        .finish()
+        .with_context(|| "failed to write the bundle")?;
+    Ok(manifest)
+}
+
+/// Unpacks the bundle at `archive` into `destination`, which must not exist
+/// yet, and checks every file against the manifest. Fails if anything is
+/// missing, modified or not listed.
+pub fn extract_bundle(archive: &Path, destination: &Path) -> anyhow::Result<BundleManifest> {
+    if destination.exists() {
+        bail!("{} already exists", destination.to_string_lossy());
+    }
+    let archive_file = File::open(archive)
+        .with_context(|| format!("failed to open {}", archive.to_string_lossy()))?;
+    tar::Archive::new(GzDecoder::new(archive_file))
+        .unpack(destination)
+        .with_context(|| "failed to unpack the bundle. is it a vexmason bundle?")?;
+
+    let manifest: BundleManifest = serde_json::from_str(
+        &std::fs::read_to_string(destination.join(BUNDLE_MANIFEST_FILE))
+            .with_context(|| format!("the bundle doesn't have a {}", BUNDLE_MANIFEST_FILE))?,
+    )
+    .with_context(|| format!("failed to parse {}", BUNDLE_MANIFEST_FILE))?;
+
+    for (name, path) in list_files(destination, "")? {
+        if name == BUNDLE_MANIFEST_FILE {
+            continue;
+        }
+        let Some(expected) = manifest.checksums.get(&name) else {
+            bail!("{} isn't listed in {}", name, BUNDLE_MANIFEST_FILE);
+        };
+        let actual = hash_file(&path)?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            bail!(
+                "checksum mismatch for {}: expected {}, got {}. the bundle may be corrupted or tampered with",
+                name,
+                expected,
+                actual
+            );
+        }
+    }
+    for name in manifest.checksums.keys() {
+        if !destination.join(name).is_file() {
+            bail!("{} is missing from the bundle", name);
+        }
+    }
+    Ok(manifest)
+}
+
+/// Lists every file under `directory` recursively, along with its name
+/// relative to `directory` prefixed with `prefix`.
+fn list_files(directory: &Path, prefix: &str) -> anyhow::Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(directory)
+        .with_context(|| format!("failed to read {}", directory.to_string_lossy()))?
+    {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let name = if prefix.is_empty() {
+            file_name
+        } else {
+            format!("{}/{}", prefix, file_name)
+        };
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            files.extend(list_files(&entry.path(), &name)?);
+        } else if file_type.is_file() {
+            files.push((name, entry.path()));
+        } else {
+            bail!(
+                "{} isn't a regular file or directory",
+                entry.path().to_string_lossy()
+            );
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn hash_file(path: &Path) -> anyhow::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut file =
+        File::open(path).with_context(|| format!("failed to read {}", path.to_string_lossy()))?;
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
@@ -3,6 +3,8 @@ use std::fmt;
 use std::io::Read;
 use std::process::{Command, Stdio};
 
+use crate::python::find_python;
+
 #[derive(Debug)]
 pub enum VersionError {
     NotFound {
@@ -41,18 +43,41 @@ impl fmt::Display for VersionError {
     }
 }
 
-pub fn check_versions() -> Result<(), VersionError> {
-    check_version("python", "Python ", "^3.10")?;
-    check_version("git", "git version ", "^2.40")?;
+/// Checks that the programs vexmason needs are installed. git is only needed
+/// when python-compiler is installed with git, so it's only checked if
+/// `require_git` is set.
+pub fn check_versions(require_git: bool) -> Result<(), VersionError> {
+    check_python_version()?;
+    if require_git {
+        check_git_version()?;
+    }
     Ok(())
 }
 
-fn check_version(prog: &str, stdout_prefix: &str, req: &str) -> Result<(), VersionError> {
+/// Checks that a supported version of Python is installed and returns it.
+/// See [`find_python`] for how the interpreter is picked.
+pub fn check_python_version() -> Result<semver::Version, VersionError> {
+    Ok(find_python(None, None)?.version)
+}
+
+/// Checks that a supported version of git is installed and returns it.
+pub fn check_git_version() -> Result<semver::Version, VersionError> {
+    check_program_version("git", &[], "git version ", "^2.40")
+}
+
+/// Runs `prog` with `args` and `--version`, and checks the version it prints
+/// after `stdout_prefix` against `req`.
+pub(crate) fn check_program_version(
+    prog: &str,
+    args: &[&str],
+    stdout_prefix: &str,
+    req: &str,
+) -> Result<semver::Version, VersionError> {
     let version_requirement = semver::VersionReq::parse(req).unwrap();
-    let version = semver::Version::parse(&version(prog, stdout_prefix)?)
+    let version = semver::Version::parse(&version(prog, args, stdout_prefix)?)
         .map_err(|_| VersionError::ParseFailure)?;
     if version_requirement.matches(&version) {
-        Ok(())
+        Ok(version)
     } else {
         Err(VersionError::BadVersion {
             prog: prog.into(),
@@ -62,8 +87,9 @@ fn check_version(prog: &str, stdout_prefix: &str, req: &str) -> Result<(), Versi
     }
 }
 
-fn version(prog: &str, prefix: &str) -> Result<String, VersionError> {
+fn version(prog: &str, args: &[&str], prefix: &str) -> Result<String, VersionError> {
     let cmd = Command::new(prog)
+        .args(args)
         .arg("--version")
         .stdout(Stdio::piped())
         .spawn();
@@ -1,11 +1,24 @@
-use std::{collections::HashMap, ffi::OsString, path::Path, process::Stdio, str::FromStr};
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Stdio,
+    str::FromStr,
+    time::Duration,
+};
 
 use anyhow::{bail, Context};
 use log::{debug, error, info};
 use serde_json::Value;
-use tokio::{io::AsyncReadExt, process::Command};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 
-use crate::config::ConfigDefineType;
+use crate::{
+    config::{ConfigDefineType, CONFIG_FILE},
+    diagnostic::Diagnostic,
+    minify::{minify, minify_with_line_map},
+    python::PythonInterpreter,
+    source_map::{source_map_path, SourceMap},
+};
 
 pub struct CompileFileOptions<'a> {
     pub input: &'a Path,
@@ -13,33 +26,109 @@ pub struct CompileFileOptions<'a> {
     pub minify: bool,
     pub defines: &'a HashMap<String, ConfigDefineType>,
     pub app_data_location: &'a Path,
+    /// source map paths are made relative to this
+    pub project_root: &'a Path,
+    pub python: &'a PythonInterpreter,
+    /// the compiler is stopped if it runs for longer than this
+    pub timeout: Duration,
+    /// modules whose imports are removed, e.g. `vex`
+    pub remove_imports: &'a [String],
+    pub prelude: &'a str,
+    pub export_dictionary_mode: &'a str,
 }
 
-pub async fn compile_file<'a>(options: &CompileFileOptions<'a>) -> anyhow::Result<Option<String>> {
+/// Gets the directory python-compiler is installed in, relative to the
+/// running executable.
+pub fn lib_dir() -> anyhow::Result<PathBuf> {
     let mut lib_dir = std::env::current_exe()?;
     lib_dir.pop();
     lib_dir.pop();
     lib_dir.push("lib");
-    let mut transformer_child = Command::new("python");
+    Ok(lib_dir)
+}
+
+/// The python-compiler revision (a tag or commit) this version of vexmason is
+/// made for. The installer checks out exactly this revision, and
+/// [`compile_file`] refuses to run any other since it relies on
+/// python-compiler's exact flags. Bump this when updating to a newer
+/// python-compiler, preferably to a full commit hash so the check compares the
+/// installed commit rather than a name.
+///
+/// This is still `HEAD` (the default branch) because python-compiler has no
+/// revision that's been confirmed to work with these flags. Replace it with the
+/// commit hash of a tested revision before releasing.
+pub const PYTHON_COMPILER_REVISION: &str = "HEAD";
+
+/// Written into the python-compiler directory by the installer. The first line
+/// is the revision that was asked for and the second the commit it resolved
+/// to, if it's known.
+pub const PYTHON_COMPILER_REVISION_FILE: &str = ".vexmason-revision";
+
+/// Whether `revision` is a full commit hash rather than a tag or branch.
+pub fn is_commit_hash(revision: &str) -> bool {
+    revision.len() == 40 && revision.chars().all(|x| x.is_ascii_hexdigit())
+}
+
+/// Checks that the python-compiler in `compiler_dir` is the revision this
+/// version of vexmason needs and returns the commit that's installed, if it was
+/// recorded.
+pub fn check_compiler_revision(compiler_dir: &Path) -> anyhow::Result<Option<String>> {
+    let content = match std::fs::read_to_string(compiler_dir.join(PYTHON_COMPILER_REVISION_FILE)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => bail!(
+            "the installed python-compiler doesn't record its revision, so it might not work with this version of vexmason. re-run the vexmason installer to install revision {}",
+            PYTHON_COMPILER_REVISION
+        ),
+        Err(e) => Err(e).with_context(|| "failed to read the python-compiler revision")?,
+    };
+    let mut lines = content.lines().map(str::trim);
+    let revision = lines.next().unwrap_or_default();
+    let commit = lines.next().filter(|x| !x.is_empty());
+    let matches = if is_commit_hash(PYTHON_COMPILER_REVISION) {
+        commit.is_some_and(|x| x.eq_ignore_ascii_case(PYTHON_COMPILER_REVISION))
+    } else {
+        revision == PYTHON_COMPILER_REVISION
+    };
+    if !matches {
+        bail!(
+            "python-compiler {} is installed, but this version of vexmason needs {}. re-run the vexmason installer",
+            commit.unwrap_or(revision),
+            PYTHON_COMPILER_REVISION
+        );
+    }
+    Ok(commit.map(str::to_string))
+}
+
+pub async fn compile_file<'a>(options: &CompileFileOptions<'a>) -> anyhow::Result<Option<String>> {
+    let lib_dir = lib_dir()?;
+    let commit = check_compiler_revision(&lib_dir.join("python-compiler"))?;
+    debug!("python-compiler commit => {:?}", commit);
+    let mut transformer_child = options.python.command();
     let mut args: Vec<OsString> = Vec::new();
     args.extend_from_slice(
         &["-m", "python-compiler", "--input"].map(|x| OsString::from_str(x).unwrap()),
     );
     args.push(dunce::canonicalize(options.input)?.as_os_str().to_owned());
+    for module in options.remove_imports {
+        args.extend_from_slice(
+            &["--remove-imports", module].map(|x| OsString::from_str(x).unwrap()),
+        );
+    }
+    if !options.prelude.is_empty() {
+        args.extend_from_slice(
+            &["--prelude", options.prelude].map(|x| OsString::from_str(x).unwrap()),
+        );
+    }
     args.extend_from_slice(
         &[
-            "--remove-imports",
-            "vex",
-            "--prelude",
-            "from vex import *",
             "--json",
             "--export-dictionary-mode",
-            "class_instance",
+            options.export_dictionary_mode,
         ]
         .map(|x| OsString::from_str(x).unwrap()),
     );
     for (k, v) in options.defines {
-        let value = Into::<String>::into(v.clone());
+        let value = v.to_python_literal();
         args.extend_from_slice(
             &["--define-constant", k, &value].map(|x| OsString::from_str(x).unwrap()),
         );
@@ -49,7 +138,10 @@ pub async fn compile_file<'a>(options: &CompileFileOptions<'a>) -> anyhow::Resul
         args.push(path.as_os_str().to_owned());
     }
 
-    info!("running python to compile entry file");
+    info!(
+        "running {} (Python {}) to compile entry file",
+        options.python, options.python.version
+    );
     debug!("args => {:?}", args);
 
     #[cfg(target_os = "windows")]
@@ -63,13 +155,43 @@ pub async fn compile_file<'a>(options: &CompileFileOptions<'a>) -> anyhow::Resul
         .stderr(Stdio::piped())
         .stdin(Stdio::null());
     let mut transformer_child = transformer_child
+        .kill_on_drop(true)
         .spawn()
         .with_context(|| "failed to execute run the python compiler")?;
 
-    let exit_status = transformer_child
-        .wait()
-        .await
-        .with_context(|| "failed to wait on child")?;
+    // both pipes are read while the compiler runs, since it blocks once a pipe
+    // is full
+    let stdout = transformer_child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("failed to secure child stdout"))?;
+    let stderr = transformer_child
+        .stderr
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("failed to secure child stderr"))?;
+    let run = async {
+        tokio::join!(
+            transformer_child.wait(),
+            // without an output file, stdout is the compiled program
+            read_lines(stdout, options.output.is_some()),
+            read_lines(stderr, true),
+        )
+    };
+    let (exit_status, stdout, stderr) = match tokio::time::timeout(options.timeout, run).await {
+        Ok((exit_status, stdout, stderr)) => (
+            exit_status.with_context(|| "failed to wait on child")?,
+            stdout.with_context(|| "failed to read compiler stdout")?,
+            stderr.with_context(|| "failed to read compiler stderr")?,
+        ),
+        Err(_) => {
+            transformer_child.kill().await.ok();
+            bail!(
+                "python-compiler didn't finish within {} seconds and was stopped. if your project is just big, raise `compile_timeout` in {}",
+                options.timeout.as_secs(),
+                CONFIG_FILE
+            );
+        }
+    };
 
     if exit_status.success() {
         info!("compiled successfully")
@@ -78,30 +200,91 @@ pub async fn compile_file<'a>(options: &CompileFileOptions<'a>) -> anyhow::Resul
     }
 
     if exit_status.success() {
-        if options.output.is_none() {
-            let mut stdout = transformer_child.stdout.take().unwrap();
-            let mut read = Vec::new();
-            stdout.read_to_end(&mut read).await?;
-            let output_value: serde_json::Value = serde_json::from_slice(&read)?;
+        if let Some(path) = options.output {
+            let output = tokio::fs::read_to_string(path)
+                .await
+                .with_context(|| "failed to read compiler output")?;
+            let mut source_map = SourceMap::generate(&output, options.input, options.project_root)
+                .await
+                .with_context(|| "failed to generate source map")?;
+            if options.minify {
+                info!("minifying output");
+                let (minified, line_map) = minify_with_line_map(&output);
+                log_minified_size(&output, &minified);
+                source_map = source_map.remap(&line_map);
+                tokio::fs::write(path, minified)
+                    .await
+                    .with_context(|| "failed to write minified output")?;
+            }
+            let map_path = source_map_path(path);
+            source_map.write(&map_path).await?;
+            info!(
+                "wrote source map with {} ranges to {}",
+                source_map.ranges.len(),
+                map_path.to_string_lossy()
+            );
+            Ok(None)
+        } else {
+            let output_value: serde_json::Value = serde_json::from_slice(&stdout)?;
             if let Some(Value::String(string)) = output_value.get("output") {
-                // probably can't avoid clone
-                Ok(Some(string.to_string()))
+                if options.minify {
+                    info!("minifying output");
+                    let minified = minify(string);
+                    log_minified_size(string, &minified);
+                    Ok(Some(minified))
+                } else {
+                    // probably can't avoid clone
+                    Ok(Some(string.to_string()))
+                }
             } else {
                 bail!("transform failed: failed to read output")
             }
-        } else {
-            Ok(None)
         }
     } else {
-        let mut stderr = transformer_child.stderr.take().unwrap();
-        let mut read = Vec::new();
-        stderr.read_to_end(&mut read).await?;
-        let output_value: serde_json::Value = serde_json::from_slice(&read)?;
-        if let (Some(Value::String(error_name)), Some(Value::String(error_msg))) =
-            (output_value.get("name"), output_value.get("msg"))
-        {
-            bail!(format!("transform failed ({error_name}): {error_msg}"))
+        let stderr = String::from_utf8_lossy(&stderr);
+        match Diagnostic::parse(&stderr) {
+            Some(mut diagnostic) => {
+                diagnostic
+                    .resolve(
+                        options.project_root,
+                        options.input.parent().unwrap_or(options.project_root),
+                    )
+                    .await;
+                bail!("transform failed\n{}", diagnostic)
+            }
+            // e.g. python-compiler itself crashed with a traceback
+            None => bail!(
+                "transform failed. python-compiler output:\n{}",
+                stderr.trim_end()
+            ),
         }
-        bail!("transform failed: failed to read error")
     }
 }
+
+/// Reads `reader` to the end, logging each line as it arrives if `log` is set.
+async fn read_lines(reader: impl AsyncRead + Unpin, log: bool) -> std::io::Result<Vec<u8>> {
+    let mut reader = BufReader::new(reader);
+    let mut content = Vec::new();
+    loop {
+        let start = content.len();
+        if reader.read_until(b'\n', &mut content).await? == 0 {
+            break;
+        }
+        if log {
+            info!(
+                "python-compiler: {}",
+                String::from_utf8_lossy(&content[start..]).trim_end()
+            );
+        }
+    }
+    Ok(content)
+}
+
+fn log_minified_size(source: &str, minified: &str) {
+    info!(
+        "minified output from {} bytes to {} bytes ({:.1}% of original)",
+        source.len(),
+        minified.len(),
+        minified.len() as f64 / source.len().max(1) as f64 * 100.0
+    );
+}
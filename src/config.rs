@@ -1,7 +1,7 @@
 mod model;
 mod template;
 
-use std::{collections::HashMap, path::Path};
+use std::{collections::HashMap, path::Path, time::Duration};
 
 use anyhow::{bail, Context};
 use log::{error, info, warn};
@@ -11,20 +11,70 @@ use tokio::{
     io::{self, AsyncReadExt},
 };
 
-pub use model::{ConfigDefineType, ResolvedConfig};
+pub use model::{
+    ConfigDefine, ConfigDefineType, DefineConstraints, DefineError, DefineInfo, DefineMetadata,
+    DefineOption, DefineOptionInfo, DefineSource, ResolvedConfig, CURRENT_CONFIG_VERSION,
+    NUMBER_TYPES_CONFIG_VERSION,
+};
 
-use self::{model::CURRENT_CONFIG_VERSION, template::evaluate_template};
+use self::template::evaluate_template;
 
 const DEFAULT_DESCRIPTION: &str = "compiled by vexmason
 at {{ time/hour }}:{{ time/minute }}
 by {{ computer-name }} | {{ language::short }} | min: {{ minify::short }}
+| profile: {{ profile }}
 | {{ defines::count }} defines:
 {{ defines::list }}
 ";
 const DEFAULT_MINIFY: bool = false;
+const DEFAULT_COMPILE_TIMEOUT_SECS: u64 = 120;
+const DEFAULT_REMOVE_IMPORTS: [&str; 1] = ["vex"];
+const DEFAULT_PRELUDE: &str = "from vex import *";
+const DEFAULT_EXPORT_DICTIONARY_MODE: &str = "class_instance";
 
 pub const CONFIG_FILE: &str = "vexmason-config.json";
 pub const CONFIG_OVERRIDES_FILE: &str = "vexmason-local-config.json";
+/// created by the VEX extension; required for a directory to count as a project
+pub const VEX_PROJECT_SETTINGS_FILE: &str = "vex_project_settings.json";
+/// takes precedence over the profile selected in the local config
+pub const PROFILE_ENV_VAR: &str = "VEXMASON_PROFILE";
+/// `VEXMASON_DEFINE_<KEY>` sets the define `<KEY>`, taking precedence over the
+/// config files
+pub const DEFINE_ENV_VAR_PREFIX: &str = "VEXMASON_DEFINE_";
+
+/// Overrides that don't come from the config files, e.g. from the command
+/// line. They take precedence over everything else.
+#[derive(Debug, Clone, Default)]
+pub struct CliOverrides {
+    pub profile: Option<String>,
+    pub defines: Vec<(String, ConfigDefineType)>,
+}
+
+/// Parses a `KEY=VALUE` define. The value is read as JSON if possible (so
+/// `true` and `1.5` are a boolean and a number), and as a string otherwise.
+pub fn parse_define(define: &str) -> anyhow::Result<(String, ConfigDefineType)> {
+    let (key, value) = define
+        .split_once('=')
+        .with_context(|| anyhow::anyhow!("define '{}' must be of the form KEY=VALUE", define))?;
+    if key.is_empty() {
+        bail!("define '{}' is missing a name", define);
+    }
+    Ok((key.to_string(), parse_define_value(value)))
+}
+
+/// Parses the value of a define given as text, the same way as
+/// [`parse_define`].
+pub fn parse_define_value(value: &str) -> ConfigDefineType {
+    serde_json::from_str::<ConfigDefineType>(value)
+        .unwrap_or_else(|_| ConfigDefineType::String(value.to_string()))
+}
+
+/// Whether a config was written for a version before integers and floats were
+/// told apart, i.e. before [`NUMBER_TYPES_CONFIG_VERSION`].
+fn uses_legacy_numbers(config_version: &str) -> bool {
+    semver::VersionReq::parse(&format!(">{}", config_version))
+        .is_ok_and(|x| x.matches(&NUMBER_TYPES_CONFIG_VERSION))
+}
 
 pub fn root(entry_point: &Path) -> Option<std::path::PathBuf> {
     let mut buf = entry_point.to_path_buf();
@@ -34,13 +84,15 @@ pub fn root(entry_point: &Path) -> Option<std::path::PathBuf> {
     while buf.is_dir() {
         if buf
             .join(".vscode")
-            .join("vex_project_settings.json")
+            .join(VEX_PROJECT_SETTINGS_FILE)
             .is_file()
             && buf.join(".vscode").join(CONFIG_FILE).is_file()
         {
             return Some(buf);
         }
-        buf.pop();
+        if !buf.pop() {
+            break;
+        }
     }
     None
 }
@@ -49,6 +101,7 @@ async fn resolved_config_from_files(
     config_path: &Path,
     config_overrides_path: &Path,
     project_root: &Path,
+    cli_overrides: &CliOverrides,
 ) -> anyhow::Result<ResolvedConfig> {
     let config = config_from_file(config_path).await.with_context(|| {
         format!(
@@ -80,33 +133,99 @@ async fn resolved_config_from_files(
         );
         bail!("the version specified in your config is not supported by your installation of vexmason. try updating.");
     }
+    let legacy_numbers = uses_legacy_numbers(&config.config_version);
 
     // resolve defines
-    let default_defines = config.default_defines.unwrap_or_else(|| HashMap::new());
+    let default_defines = config.default_defines.unwrap_or_default();
     let mut resolved_defines = HashMap::new();
+    let mut define_sources = HashMap::new();
     for (define, value) in &default_defines {
-        if !value.validate_default() {
-            bail!("the default define defined in {} did not pass its own type validation. check that `default_defines.{}.default` is contained in `default_defines.{}.options`.", CONFIG_FILE, define, define);
+        if let Err(err) = value.validate_default(legacy_numbers) {
+            bail!(
+                "the default of `default_defines.{}` in {} isn't valid: {}",
+                define,
+                CONFIG_FILE,
+                err
+            );
         }
-        resolved_defines.insert(define.to_owned(), value.to_owned().into());
+        resolved_defines.insert(define.to_owned(), value.default_value().clone());
+        define_sources.insert(define.to_owned(), DefineSource::Default);
     }
-    if let Some(defines_overrides) = config_overrides.defines_overrides {
-        for (define_override, value) in defines_overrides {
-            if resolved_defines.get(&define_override).is_some() {
-                if let Some(default) = default_defines.get(&define_override) {
-                    if default.validate(&value) {
-                        info!(
-                            "overriding define with local value: {} = {}",
-                            define_override, value
-                        );
-                        resolved_defines.insert(define_override, value);
+
+    // resolve the active profile
+    let profile_name = match (&cli_overrides.profile, std::env::var(PROFILE_ENV_VAR)) {
+        (Some(profile), _) => {
+            info!("using profile '{}' from the command line", profile);
+            Some(profile.to_owned())
+        }
+        (None, Ok(profile)) if !profile.is_empty() => {
+            info!("using profile '{}' from {}", profile, PROFILE_ENV_VAR);
+            Some(profile)
+        }
+        _ => config_overrides.profile.clone(),
+    };
+    let mut profiles = config.profiles.unwrap_or_default();
+    let profile = match &profile_name {
+        Some(profile_name) => {
+            let profile = profiles.remove(profile_name).with_context(|| {
+                let mut available = profiles.keys().cloned().collect::<Vec<_>>();
+                available.sort();
+                anyhow::anyhow!(
+                    "the profile '{}' isn't defined in `profiles` in {}. available profiles: {}",
+                    profile_name,
+                    CONFIG_FILE,
+                    if available.is_empty() {
+                        "(none)".to_string()
                     } else {
-                        bail!(
-                            "local config defines '{}' with the value '{}', but the default for that define in {} doesn't allow that type. make sure it's either included in `default_defines.{}.options` (if that exists) or the type is the same as the default if `default_defines.{}.typed` is `true`",
-                            define_override, value, CONFIG_FILE, define_override, define_override
-                        );
+                        available.join(", ")
                     }
+                )
+            })?;
+            info!("using profile '{}'", profile_name);
+            Some(profile)
+        }
+        None => None,
+    };
+
+    if let Some(profile_defines) = profile.as_ref().and_then(|x| x.defines.as_ref()) {
+        for (profile_define, value) in profile_defines {
+            let default = default_defines.get(profile_define).with_context(|| {
+                anyhow::anyhow!(
+                    "profile '{}' defines '{}' without a default value being present in `default_defines`",
+                    profile_name.as_deref().unwrap_or_default(),
+                    profile_define
+                )
+            })?;
+            if let Err(err) = default.validate(value, legacy_numbers) {
+                bail!(
+                    "profile '{}' defines '{}' with the value '{}', but `default_defines.{}` in {} doesn't allow it: {}",
+                    profile_name.as_deref().unwrap_or_default(), profile_define, value, profile_define, CONFIG_FILE, err
+                );
+            }
+            info!(
+                "overriding define with profile value: {} = {}",
+                profile_define, value
+            );
+            resolved_defines.insert(profile_define.to_owned(), default.coerce(value.to_owned()));
+            define_sources.insert(profile_define.to_owned(), DefineSource::Profile);
+        }
+    }
+
+    if let Some(defines_overrides) = config_overrides.defines_overrides {
+        for (define_override, value) in defines_overrides {
+            if let Some(default) = default_defines.get(&define_override) {
+                if let Err(err) = default.validate(&value, legacy_numbers) {
+                    bail!(
+                        "local config defines '{}' with the value '{}', but `default_defines.{}` in {} doesn't allow it: {}",
+                        define_override, value, define_override, CONFIG_FILE, err
+                    );
                 }
+                info!(
+                    "overriding define with local value: {} = {}",
+                    define_override, value
+                );
+                resolved_defines.insert(define_override.clone(), default.coerce(value));
+                define_sources.insert(define_override, DefineSource::LocalConfig);
             } else {
                 warn!(
                     "local config defines '{}' without a default value being present in the main config file, ignoring",
@@ -116,33 +235,110 @@ async fn resolved_config_from_files(
         }
     }
 
-    let computer_name = config_overrides.computer_name.as_ref().map_or_else(
-        || {
+    let mut env_defines: Vec<(String, String)> = std::env::vars()
+        .filter_map(|(var, value)| {
+            var.strip_prefix(DEFINE_ENV_VAR_PREFIX)
+                .map(|define| (define.to_string(), value))
+        })
+        .collect();
+    env_defines.sort();
+    for (env_define, value) in env_defines {
+        let var = format!("{}{}", DEFINE_ENV_VAR_PREFIX, env_define);
+        let Some(default) = default_defines.get(&env_define) else {
+            // the variable might be meant for another project
+            warn!(
+                "{} is set, but '{}' isn't in `default_defines`, ignoring",
+                var, env_define
+            );
+            continue;
+        };
+        let value = parse_define_value(&value);
+        if let Err(err) = default.validate(&value, legacy_numbers) {
+            bail!(
+                "{} sets '{}' to '{}', but `default_defines.{}` in {} doesn't allow it: {}",
+                var,
+                env_define,
+                value,
+                env_define,
+                CONFIG_FILE,
+                err
+            );
+        }
+        info!(
+            "overriding define with environment value from {}: {} = {}",
+            var, env_define, value
+        );
+        resolved_defines.insert(env_define.clone(), default.coerce(value));
+        define_sources.insert(env_define, DefineSource::Environment);
+    }
+
+    for (cli_define, value) in &cli_overrides.defines {
+        let default = default_defines.get(cli_define).with_context(|| {
+            anyhow::anyhow!(
+                "the command line defines '{}' without a default value being present in `default_defines`",
+                cli_define
+            )
+        })?;
+        if let Err(err) = default.validate(value, legacy_numbers) {
+            bail!(
+                "the command line defines '{}' with the value '{}', but `default_defines.{}` in {} doesn't allow it: {}",
+                cli_define, value, cli_define, CONFIG_FILE, err
+            );
+        }
+        info!(
+            "overriding define with command line value: {} = {}",
+            cli_define, value
+        );
+        resolved_defines.insert(cli_define.to_owned(), default.coerce(value.to_owned()));
+        define_sources.insert(cli_define.to_owned(), DefineSource::CommandLine);
+    }
+
+    let mut final_defines: Vec<_> = resolved_defines.iter().collect();
+    final_defines.sort_by(|a, b| a.0.cmp(b.0));
+    for (define, value) in final_defines {
+        info!(
+            "define {} = {} (from {})",
+            define, value, define_sources[define]
+        );
+    }
+
+    let computer_name = config_overrides
+        .computer_name
+        .as_deref()
+        .unwrap_or_else(|| {
             warn!("computer name not specified in config, defaulting to 'unknown'");
             "unknown"
-        },
-        |x| x,
-    );
+        });
 
-    let minify = config.minify.unwrap_or(DEFAULT_MINIFY);
+    let minify = profile
+        .as_ref()
+        .and_then(|x| x.minify)
+        .or(config.minify)
+        .unwrap_or(DEFAULT_MINIFY);
 
     let resolved_name = evaluate_template(
-        &config.name,
+        profile
+            .as_ref()
+            .and_then(|x| x.name.as_ref())
+            .unwrap_or(&config.name),
         computer_name,
         &config.language,
         minify,
+        profile_name.as_deref(),
         &resolved_defines,
     )
     .to_string();
 
     let resolved_description = evaluate_template(
-        config
-            .description
+        profile
             .as_ref()
+            .and_then(|x| x.description.as_ref())
+            .or(config.description.as_ref())
             .map_or(&DEFAULT_DESCRIPTION.replace('\n', " "), |x| x),
         computer_name,
         &config.language,
         minify,
+        profile_name.as_deref(),
         &resolved_defines,
     )
     .to_string();
@@ -151,23 +347,64 @@ async fn resolved_config_from_files(
         project_root.join(config.entry_file.unwrap_or("src/main.py".to_string())),
     )?;
 
+    let prelude = match (config.prelude, config.prelude_file) {
+        (Some(_), Some(_)) => bail!(
+            "{} sets both `prelude` and `prelude_file`; only one can be used",
+            CONFIG_FILE
+        ),
+        (Some(prelude), None) => prelude,
+        (None, Some(prelude_file)) => {
+            let path = project_root.join(prelude_file);
+            fs::read_to_string(&path).await.with_context(|| {
+                format!(
+                    "failed to read the prelude file at {}",
+                    path.to_string_lossy()
+                )
+            })?
+        }
+        (None, None) => DEFAULT_PRELUDE.to_string(),
+    };
+
     Ok(ResolvedConfig {
         defines: resolved_defines,
+        define_definitions: default_defines,
+        define_sources,
         description: resolved_description,
         language: config.language,
         name: resolved_name,
         project_root: project_root.to_path_buf(),
         minify,
         entry_file: resolved_entry_file,
+        profile: profile_name,
+        python: config_overrides.python.or(config.python),
+        compile_timeout: Duration::from_secs(
+            config
+                .compile_timeout
+                .unwrap_or(DEFAULT_COMPILE_TIMEOUT_SECS),
+        ),
+        remove_imports: config.remove_imports.unwrap_or_else(|| {
+            DEFAULT_REMOVE_IMPORTS
+                .iter()
+                .map(|x| x.to_string())
+                .collect()
+        }),
+        prelude,
+        export_dictionary_mode: config
+            .export_dictionary_mode
+            .unwrap_or(DEFAULT_EXPORT_DICTIONARY_MODE.to_string()),
     })
 }
 
-pub async fn resolved_config_from_root(root: &Path) -> anyhow::Result<ResolvedConfig> {
+pub async fn resolved_config_from_root(
+    root: &Path,
+    cli_overrides: &CliOverrides,
+) -> anyhow::Result<ResolvedConfig> {
     let vscode = root.join(".vscode");
     resolved_config_from_files(
         &vscode.join(CONFIG_FILE),
         &vscode.join(CONFIG_OVERRIDES_FILE),
-        &root,
+        root,
+        cli_overrides,
     )
     .await
 }
@@ -178,7 +415,7 @@ async fn config_from_file(path: &Path) -> anyhow::Result<JsonConfigV1> {
     file.read_to_string(&mut content).await?;
     let config =
         serde_json::from_str::<JsonConfigV1>(&content).with_context(|| "failed to parse config")?;
-    return Ok(config);
+    Ok(config)
 }
 
 async fn config_overrides_from_file(
@@ -196,8 +433,94 @@ async fn config_overrides_from_file(
         Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(JsonConfigV1Overrides {
             config_version: config_version.to_owned(),
             computer_name: None,
+            profile: None,
+            python: None,
             defines_overrides: None,
         }),
         Err(x) => Err(x.into()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Creates a project with the given config files in a fresh temporary
+    /// directory.
+    fn project(name: &str, config: &str, overrides: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "vexmason-config-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join(".vscode")).unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src").join("main.py"), "").unwrap();
+        std::fs::write(root.join(".vscode").join(CONFIG_FILE), config).unwrap();
+        std::fs::write(root.join(".vscode").join(CONFIG_OVERRIDES_FILE), overrides).unwrap();
+        root
+    }
+
+    fn config_with_version(version: &str) -> (String, String) {
+        (
+            format!(
+                r#"{{
+                    "config_version": "{}",
+                    "name": "test",
+                    "language": "python",
+                    "default_defines": {{
+                        "PORT": {{ "default": 1, "typed": true }},
+                        "SPEED": {{ "default": 1.0, "options": [0.5, 1.0] }}
+                    }}
+                }}"#,
+                version
+            ),
+            format!(
+                r#"{{ "config_version": "{}", "defines_overrides": {{ "PORT": 0.5 }} }}"#,
+                version
+            ),
+        )
+    }
+
+    #[tokio::test]
+    async fn numbers_in_configs_before_1_4_keep_their_meaning() {
+        let (config, overrides) = config_with_version("1.1");
+        let root = project("legacy-numbers", &config, &overrides);
+        let cli_overrides = CliOverrides {
+            profile: None,
+            defines: vec![("SPEED".to_string(), ConfigDefineType::Integer(1))],
+        };
+        let resolved = resolved_config_from_root(&root, &cli_overrides).await;
+        std::fs::remove_dir_all(&root).unwrap();
+        let resolved = resolved.unwrap();
+        assert_eq!(resolved.defines["PORT"], ConfigDefineType::Float(0.5));
+        assert_eq!(resolved.defines["SPEED"], ConfigDefineType::Integer(1));
+    }
+
+    #[tokio::test]
+    async fn numbers_in_configs_from_1_4_are_typed() {
+        let (config, overrides) = config_with_version("1.4");
+        let root = project("typed-numbers", &config, &overrides);
+        let resolved = resolved_config_from_root(&root, &CliOverrides::default()).await;
+        std::fs::remove_dir_all(&root).unwrap();
+        let err = resolved.unwrap_err();
+        assert!(
+            err.to_string().contains("must be of type integer"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[test]
+    fn legacy_number_versions() {
+        assert!(uses_legacy_numbers("1.1"));
+        assert!(uses_legacy_numbers("1.3"));
+        assert!(uses_legacy_numbers("1.3.2"));
+        assert!(!uses_legacy_numbers("1.4"));
+        assert!(!uses_legacy_numbers("1.4.0"));
+        assert!(!uses_legacy_numbers("1.5"));
+    }
+}
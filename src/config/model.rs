@@ -1,68 +1,597 @@
-use serde::{de::IgnoredAny, Deserialize, Serialize};
-use std::{collections::HashMap, fmt::Display, mem, path::PathBuf};
+use regex::Regex;
+use serde::{
+    de::{self, IgnoredAny},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::Display,
+    mem,
+    path::PathBuf,
+    time::Duration,
+};
 
-pub const CURRENT_CONFIG_VERSION: semver::Version = semver::Version::new(1, 1, 0);
+pub const CURRENT_CONFIG_VERSION: semver::Version = semver::Version::new(1, 4, 0);
+/// The first config version that tells integers and floats apart. Older configs
+/// had a single number type, so any number is accepted for a number define.
+pub const NUMBER_TYPES_CONFIG_VERSION: semver::Version = semver::Version::new(1, 4, 0);
 
+// the order of the variants matters: serde tries them from top to bottom, so
+// `1` is an integer and `1.0` is a float
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(untagged)]
 pub enum ConfigDefineType {
-    String(String),
-    Number(f32),
+    Null,
     Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    List(Vec<ConfigDefineType>),
+    Dict(BTreeMap<String, ConfigDefineType>),
 }
 
-impl Into<String> for ConfigDefineType {
-    fn into(self) -> String {
+impl ConfigDefineType {
+    /// Gets the name of the type for error messages, e.g. `integer`.
+    pub fn type_name(&self) -> &'static str {
         match self {
+            ConfigDefineType::Null => "null",
+            ConfigDefineType::Boolean(_) => "boolean",
+            ConfigDefineType::Integer(_) => "integer",
+            ConfigDefineType::Float(_) => "float",
+            ConfigDefineType::String(_) => "string",
+            ConfigDefineType::List(_) => "list",
+            ConfigDefineType::Dict(_) => "dict",
+        }
+    }
+
+    /// Checks whether `other` has the same type as this value. An integer is
+    /// accepted where a float is expected, since `1` is a valid speed of `1.0`.
+    /// With `legacy_numbers`, a float is accepted where an integer is expected
+    /// too.
+    pub fn same_type(&self, other: &ConfigDefineType, legacy_numbers: bool) -> bool {
+        match (self, other) {
+            (ConfigDefineType::Float(_), ConfigDefineType::Integer(_)) => true,
+            (ConfigDefineType::Integer(_), ConfigDefineType::Float(_)) => legacy_numbers,
+            _ => mem::discriminant(self) == mem::discriminant(other),
+        }
+    }
+
+    /// Compares two values, comparing numbers by value if `legacy_numbers` is
+    /// set, e.g. so that `1` matches an option of `1.0`.
+    fn matches(&self, other: &ConfigDefineType, legacy_numbers: bool) -> bool {
+        match (self, other) {
+            (ConfigDefineType::Integer(a), ConfigDefineType::Float(b))
+            | (ConfigDefineType::Float(b), ConfigDefineType::Integer(a))
+                if legacy_numbers =>
+            {
+                *a as f64 == *b
+            }
+            _ => self == other,
+        }
+    }
+}
+
+impl ConfigDefineType {
+    /// Encodes the value as a Python literal, e.g. `True`, `1.0` or `"a\"b"`,
+    /// so that the constant python-compiler substitutes has the intended type.
+    pub fn to_python_literal(&self) -> String {
+        match self {
+            ConfigDefineType::Null => "None".to_string(),
+            ConfigDefineType::Boolean(true) => "True".to_string(),
+            ConfigDefineType::Boolean(false) => "False".to_string(),
+            ConfigDefineType::Integer(a) => a.to_string(),
+            ConfigDefineType::Float(a) if a.is_nan() => "float(\"nan\")".to_string(),
+            ConfigDefineType::Float(a) if a.is_infinite() => {
+                format!("float(\"{}inf\")", if *a < 0.0 { "-" } else { "" })
+            }
+            // debug formatting keeps the `.0` of whole numbers, and its
+            // exponent form (e.g. `1e100`) is valid Python too
+            ConfigDefineType::Float(a) => format!("{:?}", a),
+            ConfigDefineType::String(a) => python_string_literal(a),
+            ConfigDefineType::List(a) => format!(
+                "[{}]",
+                a.iter()
+                    .map(|x| x.to_python_literal())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            ConfigDefineType::Dict(a) => format!(
+                "{{{}}}",
+                a.iter()
+                    .map(|(k, v)| format!(
+                        "{}: {}",
+                        python_string_literal(k),
+                        v.to_python_literal()
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// Quotes and escapes a string for Python source.
+fn python_string_literal(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    literal.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            // every control character is below U+0100
+            c if c.is_control() => literal.push_str(&format!("\\x{:02x}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+impl From<ConfigDefineType> for String {
+    fn from(value: ConfigDefineType) -> Self {
+        match value {
+            ConfigDefineType::Null => "null".to_string(),
             ConfigDefineType::Boolean(a) => a.to_string(),
-            ConfigDefineType::Number(a) => a.to_string(),
+            ConfigDefineType::Integer(a) => a.to_string(),
+            // debug formatting keeps the `.0` of whole numbers
+            ConfigDefineType::Float(a) => format!("{:?}", a),
             ConfigDefineType::String(a) => a,
+            ConfigDefineType::List(_) | ConfigDefineType::Dict(_) => {
+                serde_json::to_string(&value).expect("defines are always valid JSON")
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(untagged)]
 pub enum ConfigDefine {
-    /// typed simple define
-    SimpleTyped(ConfigDefineType),
-    /// typed or not typed
+    /// a value restricted to a set of options
+    Restricted {
+        default: ConfigDefineType,
+        options: Vec<DefineOption>,
+        #[serde(flatten)]
+        metadata: DefineMetadata,
+    },
+    /// typed or not typed, optionally with constraints
     ExplicitTyped {
         default: ConfigDefineType,
+        #[serde(default)]
         typed: bool,
+        #[serde(flatten)]
+        constraints: DefineConstraints,
+        #[serde(flatten)]
+        metadata: DefineMetadata,
     },
-    /// a value restricted to a set of options
-    Restricted {
-        default: ConfigDefineType,
+    /// typed simple define
+    SimpleTyped(ConfigDefineType),
+}
+
+/// Every key a define written as an object can have. Unlike an untagged enum,
+/// this rejects unknown keys, so a typo like `option` is an error instead of
+/// silently making the define untyped.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObjectDefine {
+    default: ConfigDefineType,
+    typed: Option<bool>,
+    options: Option<Vec<DefineOption>>,
+    min: Option<f64>,
+    max: Option<f64>,
+    pattern: Option<DefinePattern>,
+    max_length: Option<usize>,
+    description: Option<String>,
+    group: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for ConfigDefine {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // an object with a `default` key is always one of the object forms, so
+        // a dict define with a `default` key has to be written as
+        // `{ "default": { "default": ... } }`
+        let value = serde_json::Value::deserialize(deserializer)?;
+        if !value.as_object().is_some_and(|x| x.contains_key("default")) {
+            return ConfigDefineType::deserialize(value)
+                .map(ConfigDefine::SimpleTyped)
+                .map_err(de::Error::custom);
+        }
+        let define = ObjectDefine::deserialize(value).map_err(de::Error::custom)?;
+        let constraints = DefineConstraints {
+            min: define.min,
+            max: define.max,
+            pattern: define.pattern,
+            max_length: define.max_length,
+        };
+        if let (Some(min), Some(max)) = (constraints.min, constraints.max) {
+            if min > max {
+                return Err(de::Error::custom(format!(
+                    "`min` ({}) is greater than `max` ({})",
+                    min, max
+                )));
+            }
+        }
+        let metadata = DefineMetadata {
+            description: define.description,
+            group: define.group,
+        };
+        match define.options {
+            Some(_) if define.typed.is_some() || !constraints.is_empty() => Err(de::Error::custom(
+                "`options` can't be combined with `typed`, `min`, `max`, `pattern` or `max_length`",
+            )),
+            Some(options) => Ok(ConfigDefine::Restricted {
+                default: define.default,
+                options,
+                metadata,
+            }),
+            None => Ok(ConfigDefine::ExplicitTyped {
+                default: define.default,
+                typed: define.typed.unwrap_or(false),
+                constraints,
+                metadata,
+            }),
+        }
+    }
+}
+
+/// One of the allowed values of a restricted define.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DefineOption {
+    /// e.g. `{ "value": "route2", "label": "far side, 3 rings" }`
+    Labeled {
+        value: ConfigDefineType,
+        label: String,
+    },
+    Plain(ConfigDefineType),
+}
+
+impl DefineOption {
+    pub fn value(&self) -> &ConfigDefineType {
+        match self {
+            DefineOption::Labeled { value, .. } => value,
+            DefineOption::Plain(value) => value,
+        }
+    }
+
+    /// the text to show instead of the raw value
+    pub fn label(&self) -> Option<&str> {
+        match self {
+            DefineOption::Labeled { label, .. } => Some(label),
+            DefineOption::Plain(_) => None,
+        }
+    }
+}
+
+/// Information about a define for editors and other tooling. It doesn't
+/// affect the build.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DefineMetadata {
+    /// what the define is for
+    pub description: Option<String>,
+    /// the section to show the define in, e.g. "autonomous"
+    pub group: Option<String>,
+}
+
+/// Constraints on the value of a define. Setting any of them makes the define
+/// typed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DefineConstraints {
+    /// for numbers
+    pub min: Option<f64>,
+    /// for numbers
+    pub max: Option<f64>,
+    /// for strings; the whole string has to match
+    pub pattern: Option<DefinePattern>,
+    /// for strings, in characters
+    pub max_length: Option<usize>,
+}
+
+impl DefineConstraints {
+    fn is_empty(&self) -> bool {
+        self.min.is_none()
+            && self.max.is_none()
+            && self.pattern.is_none()
+            && self.max_length.is_none()
+    }
+
+    /// Checks that the constraints can be used with a default of this type.
+    fn check_applicable(&self, default: &ConfigDefineType) -> Result<(), DefineError> {
+        let is_number = matches!(
+            default,
+            ConfigDefineType::Integer(_) | ConfigDefineType::Float(_)
+        );
+        let is_string = matches!(default, ConfigDefineType::String(_));
+        for (constraint, is_set, applicable) in [
+            ("min", self.min.is_some(), is_number),
+            ("max", self.max.is_some(), is_number),
+            ("pattern", self.pattern.is_some(), is_string),
+            ("max_length", self.max_length.is_some(), is_string),
+        ] {
+            if is_set && !applicable {
+                return Err(DefineError::NotApplicable {
+                    constraint,
+                    type_name: default.type_name(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn check(&self, value: &ConfigDefineType) -> Result<(), DefineError> {
+        let number = match value {
+            ConfigDefineType::Integer(a) => Some(*a as f64),
+            ConfigDefineType::Float(a) => Some(*a),
+            _ => None,
+        };
+        if let (Some(min), Some(number)) = (self.min, number) {
+            if number < min {
+                return Err(DefineError::BelowMin { min });
+            }
+        }
+        if let (Some(max), Some(number)) = (self.max, number) {
+            if number > max {
+                return Err(DefineError::AboveMax { max });
+            }
+        }
+        if let ConfigDefineType::String(string) = value {
+            if let Some(max_length) = self.max_length {
+                if string.chars().count() > max_length {
+                    return Err(DefineError::TooLong { max_length });
+                }
+            }
+            if let Some(pattern) = &self.pattern {
+                if !pattern.regex.is_match(string) {
+                    return Err(DefineError::NoMatch {
+                        pattern: pattern.pattern.clone(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The `pattern` constraint, compiled once when the config is read.
+#[derive(Debug, Clone)]
+pub struct DefinePattern {
+    /// as written in the config
+    pattern: String,
+    /// `pattern`, anchored to match the whole string
+    regex: Regex,
+}
+
+impl DefinePattern {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        // checked on its own first so errors point at the user's pattern
+        Regex::new(pattern)?;
+        Ok(DefinePattern {
+            pattern: pattern.to_string(),
+            regex: Regex::new(&format!("^(?:{})$", pattern))?,
+        })
+    }
+}
+
+impl Serialize for DefinePattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.pattern)
+    }
+}
+
+impl<'de> Deserialize<'de> for DefinePattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        DefinePattern::new(&pattern).map_err(|err| {
+            de::Error::custom(format!(
+                "the pattern `{}` isn't a valid regex: {}",
+                pattern, err
+            ))
+        })
+    }
+}
+
+/// Why a define value isn't allowed.
+#[derive(Debug, Clone)]
+pub enum DefineError {
+    WrongType {
+        expected: &'static str,
+        found: &'static str,
+    },
+    NotAnOption {
         options: Vec<ConfigDefineType>,
     },
+    BelowMin {
+        min: f64,
+    },
+    AboveMax {
+        max: f64,
+    },
+    TooLong {
+        max_length: usize,
+    },
+    NoMatch {
+        pattern: String,
+    },
+    /// e.g. `min` on a string
+    NotApplicable {
+        constraint: &'static str,
+        type_name: &'static str,
+    },
+}
+
+impl Error for DefineError {}
+
+impl Display for DefineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DefineError::WrongType { expected, found } => write!(
+                f,
+                "it must be of type {} like the default, but it's of type {}",
+                expected, found
+            ),
+            DefineError::NotAnOption { options } => write!(
+                f,
+                "it must be one of `options`: {}",
+                options
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            DefineError::BelowMin { min } => write!(f, "it must be at least {} (`min`)", min),
+            DefineError::AboveMax { max } => write!(f, "it must be at most {} (`max`)", max),
+            DefineError::TooLong { max_length } => write!(
+                f,
+                "it must be at most {} characters long (`max_length`)",
+                max_length
+            ),
+            DefineError::NoMatch { pattern } => {
+                write!(f, "it must match the pattern `{}` (`pattern`)", pattern)
+            }
+            DefineError::NotApplicable {
+                constraint,
+                type_name,
+            } => write!(
+                f,
+                "`{}` can't be used with a {} default",
+                constraint, type_name
+            ),
+        }
+    }
 }
 
 impl ConfigDefine {
-    pub fn validate_default(&self) -> bool {
+    pub fn default_value(&self) -> &ConfigDefineType {
         match self {
-            ConfigDefine::Restricted { default, options } => options.contains(&default),
-            _ => true,
+            ConfigDefine::SimpleTyped(default) => default,
+            ConfigDefine::ExplicitTyped { default, .. } => default,
+            ConfigDefine::Restricted { default, .. } => default,
         }
     }
 
-    pub fn validate(&self, other: &ConfigDefineType) -> bool {
+    /// Checks that the default satisfies the define's own options and
+    /// constraints. `legacy_numbers` is set for configs older than
+    /// [`NUMBER_TYPES_CONFIG_VERSION`].
+    pub fn validate_default(&self, legacy_numbers: bool) -> Result<(), DefineError> {
         match self {
-            ConfigDefine::ExplicitTyped { default, typed } if *typed => {
-                mem::discriminant(default) == mem::discriminant(other)
+            ConfigDefine::Restricted {
+                default, options, ..
+            } => check_option(options, default, legacy_numbers),
+            ConfigDefine::ExplicitTyped {
+                default,
+                constraints,
+                ..
+            } => {
+                constraints.check_applicable(default)?;
+                constraints.check(default)
             }
-            ConfigDefine::SimpleTyped(default) => {
-                mem::discriminant(default) == mem::discriminant(other)
+            ConfigDefine::SimpleTyped(_) => Ok(()),
+        }
+    }
+
+    /// Checks that `other` can override the default. `legacy_numbers` is set
+    /// for configs older than [`NUMBER_TYPES_CONFIG_VERSION`].
+    pub fn validate(
+        &self,
+        other: &ConfigDefineType,
+        legacy_numbers: bool,
+    ) -> Result<(), DefineError> {
+        match self {
+            ConfigDefine::Restricted { options, .. } => {
+                check_option(options, other, legacy_numbers)
+            }
+            ConfigDefine::ExplicitTyped {
+                default,
+                typed,
+                constraints,
+                ..
+            } => {
+                if *typed || !constraints.is_empty() {
+                    check_type(default, other, legacy_numbers)?;
+                }
+                constraints.check(other)
             }
-            ConfigDefine::Restricted { options, .. } => options.contains(other),
-            _ => true,
+            ConfigDefine::SimpleTyped(default) => check_type(default, other, legacy_numbers),
+        }
+    }
+
+    pub fn metadata(&self) -> Option<&DefineMetadata> {
+        match self {
+            ConfigDefine::Restricted { metadata, .. } => Some(metadata),
+            ConfigDefine::ExplicitTyped { metadata, .. } => Some(metadata),
+            ConfigDefine::SimpleTyped(_) => None,
+        }
+    }
+
+    /// the allowed values, if the define is restricted to a set of options
+    pub fn options(&self) -> Option<&[DefineOption]> {
+        match self {
+            ConfigDefine::Restricted { options, .. } => Some(options),
+            _ => None,
         }
     }
-}
 
-impl Into<ConfigDefineType> for ConfigDefine {
-    fn into(self) -> ConfigDefineType {
+    /// whether values must have the same type as the default
+    pub fn is_typed(&self) -> bool {
         match self {
+            ConfigDefine::SimpleTyped(_) => true,
+            ConfigDefine::ExplicitTyped {
+                typed, constraints, ..
+            } => *typed || !constraints.is_empty(),
+            ConfigDefine::Restricted { .. } => false,
+        }
+    }
+
+    /// Converts a value that passed [`ConfigDefine::validate`] to the type of
+    /// the default, i.e. turns integers into floats for typed float defines.
+    pub fn coerce(&self, value: ConfigDefineType) -> ConfigDefineType {
+        match (self.default_value(), value) {
+            (ConfigDefineType::Float(_), ConfigDefineType::Integer(value)) if self.is_typed() => {
+                ConfigDefineType::Float(value as f64)
+            }
+            (_, value) => value,
+        }
+    }
+}
+
+fn check_option(
+    options: &[DefineOption],
+    value: &ConfigDefineType,
+    legacy_numbers: bool,
+) -> Result<(), DefineError> {
+    if options
+        .iter()
+        .any(|x| x.value().matches(value, legacy_numbers))
+    {
+        Ok(())
+    } else {
+        Err(DefineError::NotAnOption {
+            options: options.iter().map(|x| x.value().clone()).collect(),
+        })
+    }
+}
+
+fn check_type(
+    default: &ConfigDefineType,
+    other: &ConfigDefineType,
+    legacy_numbers: bool,
+) -> Result<(), DefineError> {
+    if default.same_type(other, legacy_numbers) {
+        Ok(())
+    } else {
+        Err(DefineError::WrongType {
+            expected: default.type_name(),
+            found: other.type_name(),
+        })
+    }
+}
+
+impl From<ConfigDefine> for ConfigDefineType {
+    fn from(value: ConfigDefine) -> Self {
+        match value {
             ConfigDefine::SimpleTyped(default) => default,
             ConfigDefine::ExplicitTyped { default, .. } => default,
             ConfigDefine::Restricted { default, .. } => default,
@@ -90,12 +619,42 @@ pub(super) struct JsonConfigV1 {
     pub minify: Option<bool>,
     pub default_defines: Option<HashMap<String, ConfigDefine>>,
     pub entry_file: Option<String>,
+    pub profiles: Option<HashMap<String, ConfigProfile>>,
+    /// the Python interpreter to compile with, e.g. `python3` or `py -3`
+    pub python: Option<String>,
+    /// in seconds
+    pub compile_timeout: Option<u64>,
+    /// modules provided by the brain; imports of them are left out of the
+    /// bundle
+    pub remove_imports: Option<Vec<String>>,
+    /// Python code put at the top of the bundle
+    pub prelude: Option<String>,
+    /// like `prelude`, but read from a file relative to the project root
+    pub prelude_file: Option<String>,
+    /// how python-compiler exposes the exports of each module
+    pub export_dictionary_mode: Option<String>,
 
     /// for the vscode extension
     #[serde(rename = "extension")]
     _extension: Option<IgnoredAny>,
 }
 
+/// a named set of overrides for the main config, e.g. "competition" or
+/// "skills"
+// make sure to update CURRENT_CONFIG_VERSION according to semver when updating
+// this struct
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct ConfigProfile {
+    /// needs to be parsed for placeholders
+    pub name: Option<String>,
+    /// needs to be parsed for placeholders
+    pub description: Option<String>,
+    pub minify: Option<bool>,
+    /// must only override defines present in `default_defines`
+    pub defines: Option<HashMap<String, ConfigDefineType>>,
+}
+
 // make sure to update CURRENT_CONFIG_VERSION according to semver when updating
 // this struct
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -103,6 +662,10 @@ pub(super) struct JsonConfigV1 {
 pub(super) struct JsonConfigV1Overrides {
     pub config_version: String,
     pub computer_name: Option<String>,
+    /// the profile to use, unless overridden by the environment
+    pub profile: Option<String>,
+    /// overrides `python` in the main config, e.g. for a virtual environment
+    pub python: Option<String>,
     pub defines_overrides: Option<HashMap<String, ConfigDefineType>>,
 }
 
@@ -114,13 +677,359 @@ pub struct ResolvedConfig {
     pub description: String,
     pub language: String,
     pub defines: HashMap<String, ConfigDefineType>,
+    /// `default_defines` as written in the config, with their metadata
+    pub define_definitions: HashMap<String, ConfigDefine>,
+    /// where the value of each define in `defines` came from
+    pub define_sources: HashMap<String, DefineSource>,
     pub project_root: PathBuf,
     pub minify: bool,
     pub entry_file: PathBuf,
+    /// the name of the active profile, if any
+    pub profile: Option<String>,
+    /// the configured Python interpreter; it's discovered if this isn't set
+    pub python: Option<String>,
+    pub compile_timeout: Duration,
+    pub remove_imports: Vec<String>,
+    pub prelude: String,
+    pub export_dictionary_mode: String,
+}
+
+/// The layer of configuration a define's value came from. Later layers take
+/// precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefineSource {
+    /// `default_defines` in the main config
+    Default,
+    /// the active profile
+    Profile,
+    /// `defines_overrides` in the local config
+    LocalConfig,
+    /// a `VEXMASON_DEFINE_<KEY>` environment variable
+    Environment,
+    /// `--define` on the command line
+    CommandLine,
+}
+
+impl Display for DefineSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                DefineSource::Default => "default",
+                DefineSource::Profile => "profile",
+                DefineSource::LocalConfig => "local config",
+                DefineSource::Environment => "environment",
+                DefineSource::CommandLine => "command line",
+            }
+        )
+    }
+}
+
+/// A define and its metadata, as listed by `vexmason defines`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DefineInfo {
+    pub name: String,
+    /// the value after applying the profile and overrides
+    pub value: ConfigDefineType,
+    pub source: DefineSource,
+    pub default: ConfigDefineType,
+    pub typed: bool,
+    pub description: Option<String>,
+    pub group: Option<String>,
+    pub options: Option<Vec<DefineOptionInfo>>,
+    #[serde(skip_serializing_if = "DefineConstraints::is_empty")]
+    pub constraints: DefineConstraints,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DefineOptionInfo {
+    pub value: ConfigDefineType,
+    pub label: Option<String>,
 }
 
 impl ResolvedConfig {
     pub fn build_output(&self) -> PathBuf {
         self.project_root.join("build").join("compiled.py")
     }
+
+    /// Lists every define with its metadata, sorted by group and then name.
+    /// Defines without a group come first.
+    pub fn define_infos(&self) -> Vec<DefineInfo> {
+        let mut infos: Vec<DefineInfo> = self
+            .define_definitions
+            .iter()
+            .map(|(name, define)| {
+                let metadata = define.metadata().cloned().unwrap_or_default();
+                DefineInfo {
+                    name: name.clone(),
+                    value: self
+                        .defines
+                        .get(name)
+                        .unwrap_or(define.default_value())
+                        .clone(),
+                    source: self
+                        .define_sources
+                        .get(name)
+                        .copied()
+                        .unwrap_or(DefineSource::Default),
+                    default: define.default_value().clone(),
+                    typed: define.is_typed(),
+                    description: metadata.description,
+                    group: metadata.group,
+                    options: define.options().map(|options| {
+                        options
+                            .iter()
+                            .map(|x| DefineOptionInfo {
+                                value: x.value().clone(),
+                                label: x.label().map(|x| x.to_string()),
+                            })
+                            .collect()
+                    }),
+                    constraints: match define {
+                        ConfigDefine::ExplicitTyped { constraints, .. } => constraints.clone(),
+                        _ => DefineConstraints::default(),
+                    },
+                }
+            })
+            .collect();
+        infos.sort_by(|a, b| (&a.group, &a.name).cmp(&(&b.group, &b.name)));
+        infos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn define(json: &str) -> Result<ConfigDefine, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    fn value(json: &str) -> ConfigDefineType {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn define_types() {
+        assert_eq!(value("null"), ConfigDefineType::Null);
+        assert_eq!(value("true"), ConfigDefineType::Boolean(true));
+        assert_eq!(value("1"), ConfigDefineType::Integer(1));
+        assert_eq!(value("-3"), ConfigDefineType::Integer(-3));
+        assert_eq!(value("1.0"), ConfigDefineType::Float(1.0));
+        assert_eq!(value("1e3"), ConfigDefineType::Float(1000.0));
+        assert_eq!(value(r#""a""#), ConfigDefineType::String("a".to_string()));
+        assert_eq!(
+            value(r#"[1, "a", null]"#),
+            ConfigDefineType::List(vec![
+                ConfigDefineType::Integer(1),
+                ConfigDefineType::String("a".to_string()),
+                ConfigDefineType::Null,
+            ])
+        );
+        assert_eq!(
+            value(r#"{"b": [true], "a": 0.5}"#),
+            ConfigDefineType::Dict(BTreeMap::from([
+                ("a".to_string(), ConfigDefineType::Float(0.5)),
+                (
+                    "b".to_string(),
+                    ConfigDefineType::List(vec![ConfigDefineType::Boolean(true)])
+                ),
+            ]))
+        );
+    }
+
+    #[test]
+    fn dicts_and_object_defines() {
+        // without a `default` key, an object is a dict define
+        let dict = define(r#"{"left": 1, "right": 2}"#).unwrap();
+        assert!(matches!(
+            dict,
+            ConfigDefine::SimpleTyped(ConfigDefineType::Dict(_))
+        ));
+        assert_eq!(dict.default_value(), &value(r#"{"left": 1, "right": 2}"#));
+
+        let object = define(r#"{"default": 1}"#).unwrap();
+        assert!(matches!(
+            object,
+            ConfigDefine::ExplicitTyped { typed: false, .. }
+        ));
+        assert_eq!(object.default_value(), &ConfigDefineType::Integer(1));
+
+        // a dict with a `default` key has to be wrapped
+        let wrapped = define(r#"{"default": {"default": 1, "typed": true}}"#).unwrap();
+        assert_eq!(
+            wrapped.default_value(),
+            &value(r#"{"default": 1, "typed": true}"#)
+        );
+        assert!(define(r#"{"default": 1, "typed": true, "other": 2}"#).is_err());
+    }
+
+    #[test]
+    fn integers_and_floats() {
+        let float = define("0.5").unwrap();
+        assert!(float.validate(&ConfigDefineType::Integer(1), false).is_ok());
+        assert_eq!(
+            float.coerce(ConfigDefineType::Integer(1)),
+            ConfigDefineType::Float(1.0)
+        );
+        let integer = define(r#"{"default": 1, "typed": true}"#).unwrap();
+        assert!(matches!(
+            integer.validate(&ConfigDefineType::Float(0.5), false),
+            Err(DefineError::WrongType {
+                expected: "integer",
+                found: "float"
+            })
+        ));
+        let options = define(r#"{"default": 1.0, "options": [1.0, 2.0]}"#).unwrap();
+        assert!(options
+            .validate(&ConfigDefineType::Integer(2), false)
+            .is_err());
+    }
+
+    #[test]
+    fn legacy_numbers() {
+        // configs before 1.4 had a single number type
+        let integer = define(r#"{"default": 1, "typed": true}"#).unwrap();
+        assert!(integer
+            .validate(&ConfigDefineType::Float(0.5), true)
+            .is_ok());
+        assert_eq!(
+            integer.coerce(ConfigDefineType::Float(0.5)),
+            ConfigDefineType::Float(0.5)
+        );
+        let options = define(r#"{"default": 1.0, "options": [1.0, 2.0]}"#).unwrap();
+        assert!(options
+            .validate(&ConfigDefineType::Integer(2), true)
+            .is_ok());
+        assert!(options
+            .validate(&ConfigDefineType::Integer(3), true)
+            .is_err());
+        // other types are still checked
+        assert!(integer
+            .validate(&ConfigDefineType::String("1".to_string()), true)
+            .is_err());
+    }
+
+    #[test]
+    fn python_literals() {
+        let literal = |json: &str| value(json).to_python_literal();
+        assert_eq!(literal("null"), "None");
+        assert_eq!(literal("true"), "True");
+        assert_eq!(literal("false"), "False");
+        assert_eq!(literal("-3"), "-3");
+        assert_eq!(literal("1.0"), "1.0");
+        assert_eq!(literal("0.5"), "0.5");
+        assert_eq!(literal("1e100"), "1e100");
+        assert_eq!(literal(r#""say \"hi\" \\ now""#), r#""say \"hi\" \\ now""#);
+        assert_eq!(
+            literal(r#""a\nb\tc\r\u0000\u001b\u007f""#),
+            r#""a\nb\tc\r\x00\x1b\x7f""#
+        );
+        assert_eq!(literal(r#""ünï""#), r#""ünï""#);
+        assert_eq!(
+            literal(r#"[1, [2.0, "x"], {"k": null}]"#),
+            r#"[1, [2.0, "x"], {"k": None}]"#
+        );
+        assert_eq!(
+            literal(r#"{"b": {"c": [true]}, "a\"": 1}"#),
+            r#"{"a\"": 1, "b": {"c": [True]}}"#
+        );
+    }
+
+    #[test]
+    fn non_finite_python_literals() {
+        // JSON can't express these, but they could come from elsewhere
+        assert_eq!(
+            ConfigDefineType::Float(f64::INFINITY).to_python_literal(),
+            r#"float("inf")"#
+        );
+        assert_eq!(
+            ConfigDefineType::Float(f64::NEG_INFINITY).to_python_literal(),
+            r#"float("-inf")"#
+        );
+        assert_eq!(
+            ConfigDefineType::Float(f64::NAN).to_python_literal(),
+            r#"float("nan")"#
+        );
+    }
+
+    #[test]
+    fn misspelled_define_keys_are_rejected() {
+        let err = define(r#"{"default": "a", "option": ["a", "b"]}"#).unwrap_err();
+        assert!(
+            err.to_string().contains("unknown field `option`"),
+            "{}",
+            err
+        );
+        assert!(define(r#"{"default": 1, "typd": true}"#).is_err());
+        assert!(define(r#"{"default": 1, "descripton": "speed"}"#).is_err());
+    }
+
+    #[test]
+    fn metadata_and_constraints() {
+        let define = define(
+            r#"{"default": 0.5, "min": 0, "max": 1, "description": "speed", "group": "drive"}"#,
+        )
+        .unwrap();
+        assert!(define.is_typed());
+        let metadata = define.metadata().unwrap();
+        assert_eq!(metadata.description.as_deref(), Some("speed"));
+        assert_eq!(metadata.group.as_deref(), Some("drive"));
+        assert!(define
+            .validate(&ConfigDefineType::Float(1.0), false)
+            .is_ok());
+        assert!(matches!(
+            define.validate(&ConfigDefineType::Float(1.5), false),
+            Err(DefineError::AboveMax { .. })
+        ));
+
+        let define = define_with_options();
+        assert_eq!(define.options().unwrap()[0].label(), Some("near side"));
+        assert!(define
+            .validate(&ConfigDefineType::String("route2".to_string()), false)
+            .is_ok());
+    }
+
+    fn define_with_options() -> ConfigDefine {
+        define(
+            r#"{"default": "route1", "options": [{"value": "route1", "label": "near side"}, "route2"], "group": "auto"}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn constraints_with_options_are_rejected() {
+        assert!(define(r#"{"default": 1, "options": [1, 2], "max": 2}"#).is_err());
+        assert!(define(r#"{"default": "a", "options": ["a"], "pattern": "a"}"#).is_err());
+        assert!(define(r#"{"default": "a", "options": ["a"], "typed": true}"#).is_err());
+    }
+
+    #[test]
+    fn min_greater_than_max_is_rejected() {
+        let err = define(r#"{"default": 1, "min": 5, "max": 2}"#).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("`min` (5) is greater than `max` (2)"));
+        assert!(define(r#"{"default": 2, "min": 2, "max": 2}"#).is_ok());
+    }
+
+    #[test]
+    fn patterns_are_checked_when_read() {
+        let err = define(r#"{"default": "a", "pattern": "("}"#).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("the pattern `(` isn't a valid regex"));
+
+        let define = define(r#"{"default": "route1", "pattern": "route\\d"}"#).unwrap();
+        assert!(define
+            .validate(&ConfigDefineType::String("route2".to_string()), false)
+            .is_ok());
+        // the pattern has to match the whole value
+        assert!(matches!(
+            define.validate(&ConfigDefineType::String("route22".to_string()), false),
+            Err(DefineError::NoMatch { .. })
+        ));
+    }
 }
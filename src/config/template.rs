@@ -16,6 +16,7 @@ pub fn evaluate_template<'a>(
     computer_name: &'a str,
     language: &'a str,
     minify: bool,
+    profile: Option<&'a str>,
     resolved_defines: &'a HashMap<String, ConfigDefineType>,
 ) -> Cow<'a, str> {
     TEMPLATE_REGEX.replace_all(template, |captures: &regex::Captures<'_>| {
@@ -33,6 +34,8 @@ pub fn evaluate_template<'a>(
             "minify" => minify.to_string(),
             "minify::short" => if minify { "y" } else { "n" }.to_string(),
 
+            "profile" => profile.unwrap_or("default").to_string(),
+
             "time" => Local::now().format("%a %d %b %Y, %I:%M%p").to_string(),
             "time::iso8601" => Local::now().format("%+").to_string(),
             "time/year" => Local::now().year().to_string(),
@@ -45,7 +48,7 @@ pub fn evaluate_template<'a>(
                 let mut define_str = String::new();
                 for (k, v) in resolved_defines {
                     define_str.push_str(k);
-                    define_str.push_str("=");
+                    define_str.push('=');
                     define_str.push_str(&Into::<String>::into(v.clone()));
                     define_str.push_str(", ");
                 }
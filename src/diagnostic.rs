@@ -0,0 +1,177 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+use serde::Deserialize;
+
+/// An error reported by python-compiler, e.g. a syntax error in one of the
+/// project's modules.
+///
+/// With `--json`, python-compiler writes the exception to stderr as
+/// `{"name": ..., "msg": ...}`, where `msg` is `str()` of the exception. For a
+/// `SyntaxError` that ends with ` (main.py, line 3)`, which is where the file
+/// and line come from.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Diagnostic {
+    /// the kind of error, e.g. `SyntaxError`
+    pub name: String,
+    pub msg: String,
+    /// the file name, then its path relative to the project root once
+    /// [`Diagnostic::resolve`] has found it
+    #[serde(skip)]
+    pub file: Option<String>,
+    /// 1-based
+    #[serde(skip)]
+    pub line: Option<usize>,
+    /// the source line the error is on
+    #[serde(skip)]
+    pub snippet: Option<String>,
+}
+
+impl Diagnostic {
+    /// Parses the error python-compiler writes to stderr with `--json`, or
+    /// returns `None` if stderr is something else, e.g. a traceback.
+    pub fn parse(stderr: &str) -> Option<Diagnostic> {
+        let mut diagnostic: Diagnostic = serde_json::from_str(stderr).ok()?;
+        diagnostic.locate();
+        Some(diagnostic)
+    }
+
+    /// Moves the ` (file, line N)` suffix Python adds to syntax errors out of
+    /// the message.
+    fn locate(&mut self) {
+        let location = Regex::new(r"^(?s)(.*) \(([^(),]+), line (\d+)\)$").unwrap();
+        let Some(captures) = location.captures(&self.msg) else {
+            return;
+        };
+        let Ok(line) = captures[3].parse() else {
+            return;
+        };
+        self.file = Some(captures[2].to_string());
+        self.line = Some(line);
+        self.msg = captures[1].to_string();
+    }
+
+    /// Finds the file in `source_dir` (where the entry file is) or
+    /// `project_root`, makes its path relative to `project_root` and reads the
+    /// snippet from it.
+    pub async fn resolve(&mut self, project_root: &Path, source_dir: &Path) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let Some(path) = [source_dir.join(file), project_root.join(file)]
+            .into_iter()
+            .find(|x| x.is_file())
+        else {
+            return;
+        };
+        if let (Some(line), Ok(content)) = (self.line, tokio::fs::read_to_string(&path).await) {
+            self.snippet = content
+                .lines()
+                .nth(line.saturating_sub(1))
+                .map(|x| x.to_string());
+        }
+        let path: PathBuf = dunce::canonicalize(&path).unwrap_or(path);
+        if let Ok(relative) = path
+            .strip_prefix(dunce::canonicalize(project_root).unwrap_or(project_root.to_path_buf()))
+        {
+            self.file = Some(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+}
+
+/// Renders the diagnostic like rustc does, e.g.
+///
+/// ```text
+/// error[SyntaxError]: invalid syntax
+///  --> src/main.py:3
+///   |
+/// 3 | def foo(
+/// ```
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "error[{}]: {}", self.name, self.msg)?;
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        let location = match self.line {
+            Some(line) => format!("{}:{}", file, line),
+            None => file.to_string(),
+        };
+        let gutter = self.line.map_or(0, |x| x.to_string().len());
+        write!(f, "\n{:gutter$}--> {}", "", location)?;
+        if let (Some(line), Some(snippet)) = (self.line, &self.snippet) {
+            write!(f, "\n{:gutter$} |", "")?;
+            write!(f, "\n{} | {}", line, snippet.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What python-compiler writes to stderr with `--json` for a syntax error.
+    const SYNTAX_ERROR: &str =
+        r#"{"name": "SyntaxError", "msg": "'(' was never closed (main.py, line 3)"}"#;
+
+    #[test]
+    fn syntax_errors_are_located() {
+        let diagnostic = Diagnostic::parse(SYNTAX_ERROR).unwrap();
+        assert_eq!(diagnostic.name, "SyntaxError");
+        assert_eq!(diagnostic.msg, "'(' was never closed");
+        assert_eq!(diagnostic.file.as_deref(), Some("main.py"));
+        assert_eq!(diagnostic.line, Some(3));
+        assert_eq!(
+            diagnostic.to_string(),
+            "error[SyntaxError]: '(' was never closed\n --> main.py:3"
+        );
+    }
+
+    #[test]
+    fn other_errors_keep_their_message() {
+        let diagnostic =
+            Diagnostic::parse(r#"{"name": "ImportError", "msg": "no module named 'motors'"}"#)
+                .unwrap();
+        assert_eq!(diagnostic.msg, "no module named 'motors'");
+        assert_eq!(diagnostic.file, None);
+        assert_eq!(
+            diagnostic.to_string(),
+            "error[ImportError]: no module named 'motors'"
+        );
+    }
+
+    #[test]
+    fn tracebacks_are_not_diagnostics() {
+        let traceback = "Traceback (most recent call last):\n  File \"<frozen runpy>\", line 198, in _run_module_as_main\nModuleNotFoundError: No module named 'python-compiler'\n";
+        assert!(Diagnostic::parse(traceback).is_none());
+        assert!(Diagnostic::parse("").is_none());
+        assert!(Diagnostic::parse(r#"{"output": "x = 1"}"#).is_none());
+    }
+
+    #[tokio::test]
+    async fn resolve_finds_the_file_and_snippet() {
+        let root =
+            std::env::temp_dir().join(format!("vexmason-diagnostic-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(
+            root.join("src").join("main.py"),
+            "import vex\n\nprint((1, 2)\n",
+        )
+        .unwrap();
+
+        let mut diagnostic = Diagnostic::parse(SYNTAX_ERROR).unwrap();
+        diagnostic.resolve(&root, &root.join("src")).await;
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(diagnostic.file.as_deref(), Some("src/main.py"));
+        assert_eq!(
+            diagnostic.to_string(),
+            "error[SyntaxError]: '(' was never closed\n --> src/main.py:3\n  |\n3 | print((1, 2)"
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::{
+    check_versions::check_git_version,
+    compile_file::{check_compiler_revision, PYTHON_COMPILER_REVISION},
+    config::{resolved_config_from_root, root, CliOverrides, CONFIG_FILE},
+    installation_location::{
+        find_vexcode_extensions, get_installation_path, get_vscode_extensions_directory,
+        vexcom_directory, VEXCOM_NAME, VEXCOM_OLD_NAME,
+    },
+    python::{find_python, PYTHON_ENV_VAR},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    Skip,
+}
+
+/// The result of a single diagnostic check.
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub message: String,
+    /// how to fix the problem, if there is one
+    pub suggestion: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Pass,
+            message: message.into(),
+            suggestion: None,
+        }
+    }
+
+    fn problem(
+        name: &'static str,
+        status: CheckStatus,
+        message: impl Into<String>,
+        suggestion: impl Into<String>,
+    ) -> Self {
+        Self {
+            name,
+            status,
+            message: message.into(),
+            suggestion: Some(suggestion.into()),
+        }
+    }
+}
+
+const REINSTALL_SUGGESTION: &str = "re-run the vexmason installer";
+
+/// Runs every diagnostic check. `project` is any path inside the project to
+/// validate; the project checks are skipped if it isn't inside one.
+pub async fn run_checks(project: &Path) -> Vec<Check> {
+    // the project can pick the interpreter
+    let project_root = root(project);
+    let configured_python = match &project_root {
+        Some(root) => resolved_config_from_root(root, &CliOverrides::default())
+            .await
+            .ok()
+            .and_then(|x| x.python),
+        None => None,
+    };
+    let mut checks = vec![
+        check_python(configured_python.as_deref(), project_root.as_deref()),
+        check_git(),
+    ];
+    checks.extend(check_installation());
+    checks.extend(check_vexcom_hooks().await);
+    checks.push(check_project(project).await);
+    checks
+}
+
+fn check_python(configured: Option<&str>, project_root: Option<&Path>) -> Check {
+    match find_python(configured, project_root) {
+        Ok(interpreter) => Check::pass(
+            "python",
+            format!("found {} (Python {})", interpreter, interpreter.version),
+        ),
+        Err(err) => Check::problem(
+            "python",
+            CheckStatus::Fail,
+            err.to_string(),
+            format!(
+                "install Python 3.10 or newer from python.org and make sure `python3` or `python` is on your PATH, or set {} (or `python` in the config) to the interpreter to use",
+                PYTHON_ENV_VAR
+            ),
+        ),
+    }
+}
+
+fn check_git() -> Check {
+    match check_git_version() {
+        Ok(version) => Check::pass("git", format!("found git {}", version)),
+        Err(err) => Check::problem(
+            "git",
+            CheckStatus::Warn,
+            err.to_string(),
+            "git is optional, but installing git 2.40 or newer from git-scm.com lets the installer update python-compiler with git instead of downloading it again",
+        ),
+    }
+}
+
+fn check_installation() -> Vec<Check> {
+    let installation_path = match get_installation_path(std::env::current_exe().ok().as_deref()) {
+        Ok(path) => path,
+        Err(err) => {
+            return vec![Check::problem(
+                "installation",
+                CheckStatus::Fail,
+                format!("can't determine the installation directory: {}", err),
+                REINSTALL_SUGGESTION,
+            )]
+        }
+    };
+    let mut checks = Vec::new();
+
+    let binary = installation_path
+        .join("bin")
+        .join(format!("vexmason{}", std::env::consts::EXE_SUFFIX));
+    checks.push(if binary.is_file() {
+        Check::pass(
+            "installation",
+            format!(
+                "vexmason is installed at {}",
+                installation_path.to_string_lossy()
+            ),
+        )
+    } else {
+        Check::problem(
+            "installation",
+            CheckStatus::Fail,
+            format!("{} doesn't exist", binary.to_string_lossy()),
+            REINSTALL_SUGGESTION,
+        )
+    });
+
+    let compiler_dir = installation_path.join("lib").join("python-compiler");
+    checks.push(if compiler_dir.join("__main__.py").is_file() {
+        match check_compiler_revision(&compiler_dir) {
+            Ok(commit) => Check::pass(
+                "python-compiler",
+                format!(
+                    "python-compiler {} is installed at {}",
+                    match commit {
+                        Some(commit) if commit != PYTHON_COMPILER_REVISION => {
+                            format!("{} ({})", PYTHON_COMPILER_REVISION, commit)
+                        }
+                        _ => PYTHON_COMPILER_REVISION.to_string(),
+                    },
+                    compiler_dir.to_string_lossy()
+                ),
+            ),
+            Err(err) => Check::problem(
+                "python-compiler",
+                CheckStatus::Fail,
+                err.to_string(),
+                REINSTALL_SUGGESTION,
+            ),
+        }
+    } else if compiler_dir.is_dir() {
+        Check::problem(
+            "python-compiler",
+            CheckStatus::Fail,
+            format!(
+                "{} exists but doesn't look like python-compiler",
+                compiler_dir.to_string_lossy()
+            ),
+            format!(
+                "delete {} and {}",
+                compiler_dir.to_string_lossy(),
+                REINSTALL_SUGGESTION
+            ),
+        )
+    } else {
+        Check::problem(
+            "python-compiler",
+            CheckStatus::Fail,
+            format!("{} doesn't exist", compiler_dir.to_string_lossy()),
+            REINSTALL_SUGGESTION,
+        )
+    });
+    checks
+}
+
+async fn check_vexcom_hooks() -> Vec<Check> {
+    let extensions_dir =
+        match get_vscode_extensions_directory(std::env::current_exe().ok().as_deref()) {
+            Ok(dir) => dir,
+            Err(err) => {
+                return vec![Check::problem(
+                    "vexcom",
+                    CheckStatus::Fail,
+                    format!("can't determine the VS Code extensions directory: {}", err),
+                    "make sure VS Code is installed for your user",
+                )]
+            }
+        };
+    let extensions = match find_vexcode_extensions(&extensions_dir).await {
+        Ok(extensions) if !extensions.is_empty() => extensions,
+        Ok(_) => {
+            return vec![Check::problem(
+                "vexcom",
+                CheckStatus::Fail,
+                format!(
+                    "no VEXcode extension found in {}",
+                    extensions_dir.to_string_lossy()
+                ),
+                "install the VEX Robotics extension in VS Code, then re-run the vexmason installer",
+            )]
+        }
+        Err(err) => {
+            return vec![Check::problem(
+                "vexcom",
+                CheckStatus::Fail,
+                format!("{:#}", err),
+                "make sure VS Code and the VEX Robotics extension are installed",
+            )]
+        }
+    };
+
+    let mut checks = Vec::new();
+    for extension in extensions {
+        let extension_name = extension
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let vexcom_dir = match vexcom_directory(&extension) {
+            Ok(dir) => dir,
+            Err(err) => {
+                checks.push(Check::problem(
+                    "vexcom",
+                    CheckStatus::Fail,
+                    err.to_string(),
+                    "vexmason doesn't support this platform yet",
+                ));
+                continue;
+            }
+        };
+        let has_shim = vexcom_dir.join(VEXCOM_NAME).is_file();
+        let has_old = vexcom_dir.join(VEXCOM_OLD_NAME).is_file();
+        checks.push(match (has_shim, has_old) {
+            (true, true) => Check::pass(
+                "vexcom",
+                format!("the vexcom shim is installed in {}", extension_name),
+            ),
+            (true, false) => Check::problem(
+                "vexcom",
+                CheckStatus::Fail,
+                format!(
+                    "the vexcom shim isn't installed in {} ({} is missing)",
+                    extension_name, VEXCOM_OLD_NAME
+                ),
+                "run `vexmason rehook`. VS Code installs extension updates into a new directory, which needs to be hooked again",
+            ),
+            (false, true) => Check::problem(
+                "vexcom",
+                CheckStatus::Fail,
+                format!(
+                    "{} is missing from {}, but {} is there",
+                    VEXCOM_NAME, extension_name, VEXCOM_OLD_NAME
+                ),
+                format!(
+                    "rename {} back to {} in {}, then {}",
+                    VEXCOM_OLD_NAME,
+                    VEXCOM_NAME,
+                    vexcom_dir.to_string_lossy(),
+                    REINSTALL_SUGGESTION
+                ),
+            ),
+            (false, false) => Check::problem(
+                "vexcom",
+                CheckStatus::Fail,
+                format!("no vexcom binary found in {}", vexcom_dir.to_string_lossy()),
+                "reinstall the VEX Robotics extension in VS Code",
+            ),
+        });
+    }
+    checks
+}
+
+async fn check_project(project: &Path) -> Check {
+    let Some(root) = root(project) else {
+        return Check {
+            name: "project",
+            status: CheckStatus::Skip,
+            message: "not inside a vexmason project".to_string(),
+            suggestion: Some(
+                "run this inside your project to check its config, or run `vexmason init` to create one"
+                    .to_string(),
+            ),
+        };
+    };
+    match resolved_config_from_root(&root, &CliOverrides::default()).await {
+        Ok(config) => Check::pass(
+            "project",
+            format!(
+                "the config in {} is valid ({} defines)",
+                root.to_string_lossy(),
+                config.defines.len()
+            ),
+        ),
+        Err(err) => Check::problem(
+            "project",
+            CheckStatus::Fail,
+            format!("{:#}", err),
+            format!("fix the problem in {} or the local config", CONFIG_FILE),
+        ),
+    }
+}
@@ -0,0 +1,117 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use tokio::fs;
+
+use crate::installation_location::{
+    find_vexcode_extensions, vexcom_directory, HOME_FILE_NAME, VEXCOM_BACKUP_NAME, VEXCOM_NAME,
+    VEXCOM_OLD_NAME, VEXCOM_TMP_NAME,
+};
+
+/// Gets where the installer keeps a copy of the vexcom shim, so extensions
+/// that VS Code installs later can be hooked too.
+pub fn installed_shim_path(installation_directory: &Path) -> PathBuf {
+    installation_directory.join("bin").join(VEXCOM_NAME)
+}
+
+/// Whether the vexcom shim is installed in the vexcom directory of a VEXcode
+/// extension.
+pub fn is_hooked(vexcom_dir: &Path) -> bool {
+    vexcom_dir.join(VEXCOM_OLD_NAME).is_file()
+}
+
+/// Copies the installation path recorded next to `shim` into `vexcom_dir`, or
+/// removes a stale one if `shim` has none, so the shim finds the installation.
+pub async fn sync_home_file(vexcom_dir: &Path, shim: &Path) -> anyhow::Result<()> {
+    let home_file = shim.with_file_name(HOME_FILE_NAME);
+    let target = vexcom_dir.join(HOME_FILE_NAME);
+    if home_file == target {
+        return Ok(());
+    }
+    if home_file.is_file() {
+        fs::copy(&home_file, &target)
+            .await
+            .with_context(|| format!("failed to write {}", target.to_string_lossy()))?;
+    } else if target.is_file() {
+        fs::remove_file(&target)
+            .await
+            .with_context(|| format!("failed to remove {}", target.to_string_lossy()))?;
+    }
+    Ok(())
+}
+
+/// Replaces vexcom in `vexcom_dir` with `shim`, keeping the original as
+/// `vexcom.old` and a backup.
+pub async fn install_hook(vexcom_dir: &Path, shim: &Path) -> anyhow::Result<()> {
+    sync_home_file(vexcom_dir, shim).await?;
+    fs::copy(shim, vexcom_dir.join(VEXCOM_TMP_NAME))
+        .await
+        .with_context(|| format!("failed to copy the shim from {}", shim.to_string_lossy()))?;
+    fs::copy(
+        vexcom_dir.join(VEXCOM_NAME),
+        vexcom_dir.join(VEXCOM_BACKUP_NAME),
+    )
+    .await
+    .with_context(|| format!("failed to back up {}", VEXCOM_NAME))?;
+    fs::rename(
+        vexcom_dir.join(VEXCOM_NAME),
+        vexcom_dir.join(VEXCOM_OLD_NAME),
+    )
+    .await?;
+    fs::rename(
+        vexcom_dir.join(VEXCOM_TMP_NAME),
+        vexcom_dir.join(VEXCOM_NAME),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Lists the installed VEXcode extensions that can be hooked but aren't yet.
+pub async fn unhooked_extensions(vscode_extensions_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut unhooked = Vec::new();
+    for extension in find_vexcode_extensions(vscode_extensions_dir).await? {
+        let vexcom_dir = vexcom_directory(&extension)?;
+        // extensions without vexcom for this platform can't be hooked
+        if !is_hooked(&vexcom_dir) && vexcom_dir.join(VEXCOM_NAME).is_file() {
+            unhooked.push(extension);
+        }
+    }
+    Ok(unhooked)
+}
+
+/// Hooks every installed VEXcode extension that isn't hooked yet and returns
+/// the extensions that were hooked. VS Code installs each update of the
+/// extension into a new directory, so this needs to run again after updates.
+pub async fn hook_all(vscode_extensions_dir: &Path, shim: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut hooked = Vec::new();
+    for extension in unhooked_extensions(vscode_extensions_dir).await? {
+        let vexcom_dir = vexcom_directory(&extension)?;
+        install_hook(&vexcom_dir, shim).await.with_context(|| {
+            format!(
+                "failed to hook {}",
+                extension.file_name().unwrap_or_default().to_string_lossy()
+            )
+        })?;
+        hooked.push(extension);
+    }
+    Ok(hooked)
+}
+
+/// Finds a copy of the vexcom shim, either in the installation or in an
+/// extension that's already hooked.
+pub async fn find_shim(
+    installation_directory: &Path,
+    vscode_extensions_dir: &Path,
+) -> anyhow::Result<PathBuf> {
+    let installed = installed_shim_path(installation_directory);
+    if installed.is_file() {
+        return Ok(installed);
+    }
+    for extension in find_vexcode_extensions(vscode_extensions_dir).await? {
+        let vexcom_dir = vexcom_directory(&extension)?;
+        if is_hooked(&vexcom_dir) && vexcom_dir.join(VEXCOM_NAME).is_file() {
+            return Ok(vexcom_dir.join(VEXCOM_NAME));
+        }
+    }
+    anyhow::bail!("couldn't find a copy of the vexcom shim. re-run the vexmason installer")
+}
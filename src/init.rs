@@ -0,0 +1,233 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde_json::{json, Value};
+use tokio::fs;
+
+use crate::config::{
+    CONFIG_FILE, CONFIG_OVERRIDES_FILE, CURRENT_CONFIG_VERSION, VEX_PROJECT_SETTINGS_FILE,
+};
+
+const DEFAULT_ENTRY_FILE: &str = "src/main.py";
+
+const MAIN_PY_TEMPLATE: &str =
+    "# the `vex` import is removed and replaced with a prelude by vexmason
+from vex import *
+
+brain = Brain()
+
+brain.screen.print(\"Hello from vexmason!\")
+";
+
+const GITIGNORE_ENTRIES: [&str; 2] = ["build/", ".vscode/vexmason-local-config.json"];
+
+pub struct InitOptions<'a> {
+    pub root: &'a Path,
+    /// defaults to the name in the VEXcode project settings or the name of the
+    /// directory
+    pub name: Option<&'a str>,
+}
+
+/// What [`init_project`] did to each file.
+#[derive(Debug, Clone)]
+pub enum InitAction {
+    Created(PathBuf),
+    Updated(PathBuf),
+    Skipped { path: PathBuf, reason: String },
+}
+
+/// Creates a new vexmason project in `options.root`, or converts the plain
+/// VEXcode Python project that's already there. Existing files are never
+/// overwritten.
+pub async fn init_project(options: &InitOptions<'_>) -> anyhow::Result<Vec<InitAction>> {
+    let root = options.root;
+    let vscode = root.join(".vscode");
+    fs::create_dir_all(&vscode)
+        .await
+        .with_context(|| "failed to create .vscode directory")?;
+
+    let mut actions = Vec::new();
+
+    // a plain VEXcode project already has these settings, which tell us the
+    // project name and entry file
+    let settings_path = vscode.join(VEX_PROJECT_SETTINGS_FILE);
+    let existing_settings = match fs::read_to_string(&settings_path).await {
+        Ok(content) => Some(
+            serde_json::from_str::<Value>(&content)
+                .with_context(|| format!("failed to parse {}", VEX_PROJECT_SETTINGS_FILE))?,
+        ),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            Err(e).with_context(|| format!("failed to read {}", VEX_PROJECT_SETTINGS_FILE))?
+        }
+    };
+    let directory_name = dunce::canonicalize(root)?
+        .file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_else(|| "vexmason project".to_string());
+    let name = options
+        .name
+        .map(str::to_string)
+        .or_else(|| {
+            existing_settings
+                .as_ref()
+                .and_then(|x| x.pointer("/project/name"))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+        .unwrap_or(directory_name);
+    let entry_file = existing_settings
+        .as_ref()
+        .and_then(|x| x.pointer("/project/python/main"))
+        .and_then(Value::as_str)
+        .unwrap_or(DEFAULT_ENTRY_FILE)
+        .to_string();
+
+    if let Some(settings) = &existing_settings {
+        let language = settings
+            .pointer("/project/language")
+            .and_then(Value::as_str);
+        if language.is_some_and(|x| x != "python") {
+            anyhow::bail!(
+                "this VEXcode project uses {}, but vexmason only supports python projects",
+                language.unwrap_or_default()
+            );
+        }
+        actions.push(InitAction::Skipped {
+            path: settings_path,
+            reason: "already a VEXcode project".to_string(),
+        });
+    } else {
+        let settings = json!({
+            "extension": {
+                "json": 2,
+            },
+            "project": {
+                "name": name,
+                "description": "",
+                "creationDate": chrono::Local::now().to_rfc2822(),
+                "platform": "V5",
+                "language": "python",
+                "slot": 1,
+                "python": {
+                    "main": entry_file,
+                },
+            },
+        });
+        write_json(&settings_path, &settings).await?;
+        actions.push(InitAction::Created(settings_path));
+    }
+
+    let config_version = format!(
+        "{}.{}",
+        CURRENT_CONFIG_VERSION.major, CURRENT_CONFIG_VERSION.minor
+    );
+    let mut config = json!({
+        "config_version": config_version,
+        "name": format!("{} | {{{{ profile }}}}", name),
+        "language": "python",
+        "default_defines": {},
+    });
+    if entry_file != DEFAULT_ENTRY_FILE {
+        config["entry_file"] = json!(entry_file);
+    }
+    actions.push(create_json(&vscode.join(CONFIG_FILE), &config).await?);
+
+    let mut local_config = json!({
+        "config_version": config_version,
+        "defines_overrides": {},
+    });
+    if let Some(computer_name) = computer_name() {
+        local_config["computer_name"] = json!(computer_name);
+    }
+    actions.push(create_json(&vscode.join(CONFIG_OVERRIDES_FILE), &local_config).await?);
+
+    let entry_path = root.join(&entry_file);
+    if entry_path.exists() {
+        actions.push(InitAction::Skipped {
+            path: entry_path,
+            reason: "already exists".to_string(),
+        });
+    } else {
+        if let Some(parent) = entry_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(&entry_path, MAIN_PY_TEMPLATE)
+            .await
+            .with_context(|| format!("failed to write {}", entry_file))?;
+        actions.push(InitAction::Created(entry_path));
+    }
+
+    actions.push(update_gitignore(&root.join(".gitignore")).await?);
+
+    Ok(actions)
+}
+
+fn computer_name() -> Option<String> {
+    ["COMPUTERNAME", "HOSTNAME"]
+        .into_iter()
+        .find_map(|x| std::env::var(x).ok())
+        .filter(|x| !x.is_empty())
+}
+
+async fn write_json(path: &Path, value: &Value) -> anyhow::Result<()> {
+    let mut content = serde_json::to_string_pretty(value)?;
+    content.push('\n');
+    fs::write(path, content)
+        .await
+        .with_context(|| format!("failed to write {}", path.to_string_lossy()))
+}
+
+async fn create_json(path: &Path, value: &Value) -> anyhow::Result<InitAction> {
+    if path.exists() {
+        Ok(InitAction::Skipped {
+            path: path.to_path_buf(),
+            reason: "already exists".to_string(),
+        })
+    } else {
+        write_json(path, value).await?;
+        Ok(InitAction::Created(path.to_path_buf()))
+    }
+}
+
+/// Adds the build directory and the local config to the `.gitignore`, unless
+/// they're already there.
+async fn update_gitignore(path: &Path) -> anyhow::Result<InitAction> {
+    let existing = match fs::read_to_string(path).await {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => Err(e).with_context(|| "failed to read .gitignore")?,
+    };
+    let content = existing.clone().unwrap_or_default();
+    let missing: Vec<&str> = GITIGNORE_ENTRIES
+        .into_iter()
+        .filter(|entry| {
+            !content
+                .lines()
+                .any(|line| line.trim().trim_start_matches('/') == *entry)
+        })
+        .collect();
+    if missing.is_empty() {
+        return Ok(InitAction::Skipped {
+            path: path.to_path_buf(),
+            reason: "already ignores vexmason files".to_string(),
+        });
+    }
+    let mut content = content;
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str("# vexmason\n");
+    for entry in missing {
+        content.push_str(entry);
+        content.push('\n');
+    }
+    fs::write(path, content)
+        .await
+        .with_context(|| "failed to write .gitignore")?;
+    Ok(if existing.is_some() {
+        InitAction::Updated(path.to_path_buf())
+    } else {
+        InitAction::Created(path.to_path_buf())
+    })
+}
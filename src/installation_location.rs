@@ -19,13 +19,43 @@ pub const VEXCOM_NAME: &str = "vexcom";
 #[cfg(target_os = "linux")]
 pub const VEXCOM_NAME: &str = "vexcom";
 
+/// where the shim is copied to before it replaces vexcom
+pub const VEXCOM_TMP_NAME: &str = "vexcom.tmp";
+/// a copy of the original vexcom, kept in case `vexcom.old` gets lost
+pub const VEXCOM_BACKUP_NAME: &str = "vexcom.bak";
+
 const INSTALLATION_DIRECTORY: &str = "vexmason";
 
+/// Overrides the installation path on every platform.
+pub const HOME_ENV_VAR: &str = "VEXMASON_HOME";
+/// Records a custom installation path next to the binaries and in every hooked
+/// vexcom directory, since VS Code doesn't start vexcom with `VEXMASON_HOME`.
+pub const HOME_FILE_NAME: &str = "vexmason-home";
+
 /// Gets the path of the current installation.
 ///
+/// `VEXMASON_HOME` takes precedence, followed by a [`HOME_FILE_NAME`] file
+/// next to reference_path. Otherwise, this is
+/// `%LOCALAPPDATA%\vexmason` on Windows, `$XDG_DATA_HOME/vexmason` (defaulting
+/// to `~/.local/share/vexmason`) on Linux and
+/// `~/Library/Application Support/vexmason` on macOS, unless `XDG_DATA_HOME` is
+/// set there too.
+///
 /// If it can't be determined normally (i.e., using the home directory and/or
 /// environment variables), uses reference_path to find it.
 pub fn get_installation_path(reference_path: Option<&Path>) -> anyhow::Result<PathBuf> {
+    if let Some(home) = absolute_env_var(HOME_ENV_VAR) {
+        return Ok(home);
+    }
+    if let Some(home) = reference_path.and_then(read_home_file) {
+        return Ok(home);
+    }
+    default_installation_path(reference_path)
+}
+
+/// Gets the installation path used when neither `VEXMASON_HOME` nor a
+/// [`HOME_FILE_NAME`] file says otherwise. See [`get_installation_path`].
+pub fn default_installation_path(reference_path: Option<&Path>) -> anyhow::Result<PathBuf> {
     if cfg!(target_os = "windows") {
         let local_app_data: PathBuf = match std::env::var("LOCALAPPDATA") {
             Ok(dir) => dir.into(),
@@ -62,10 +92,15 @@ pub fn get_installation_path(reference_path: Option<&Path>) -> anyhow::Result<Pa
             }
         };
         Ok(local_app_data.join(INSTALLATION_DIRECTORY))
-    } else if cfg!(target_os = "linux") {
-        Ok(PathBuf::from_str("~/.vexmason")?.join(INSTALLATION_DIRECTORY))
-    } else if cfg!(target_os = "macos") {
-        Ok(PathBuf::from_str("~/.vexmason")?.join(INSTALLATION_DIRECTORY))
+    } else if cfg!(any(target_os = "linux", target_os = "macos")) {
+        let data_home = match absolute_env_var("XDG_DATA_HOME") {
+            Some(dir) => dir,
+            None if cfg!(target_os = "macos") => home_directory()?
+                .join("Library")
+                .join("Application Support"),
+            None => home_directory()?.join(".local").join("share"),
+        };
+        Ok(data_home.join(INSTALLATION_DIRECTORY))
     } else {
         bail!("unsupported operating system")
     }
@@ -103,9 +138,108 @@ pub fn get_user_directory(reference_path: Option<&Path>) -> anyhow::Result<PathB
                 }
             }
         })
-    } else if cfg!(target_os = "linux") {
-        Ok(PathBuf::from_str("~")?)
+    } else if cfg!(any(target_os = "linux", target_os = "macos")) {
+        home_directory()
     } else {
         bail!("unsupported operating system")
     }
 }
+
+/// Gets `$HOME` on Linux and macOS.
+fn home_directory() -> anyhow::Result<PathBuf> {
+    absolute_env_var("HOME").ok_or_else(|| {
+        anyhow::anyhow!("can't find your home directory: HOME isn't set to an absolute path")
+    })
+}
+
+/// Gets an environment variable as a path, ignoring it if it's empty or
+/// relative as the XDG base directory spec requires.
+fn absolute_env_var(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .map(PathBuf::from)
+        .filter(|x| x.is_absolute())
+}
+
+/// Reads the installation path recorded in the [`HOME_FILE_NAME`] file next to
+/// `reference_path`, if there is one.
+fn read_home_file(reference_path: &Path) -> Option<PathBuf> {
+    std::fs::read_to_string(reference_path.with_file_name(HOME_FILE_NAME))
+        .ok()
+        .map(|x| PathBuf::from(x.trim_end_matches(['\r', '\n'])))
+        .filter(|x| x.is_absolute())
+}
+
+/// Gets the name of the directory the VEX extension keeps the vexcom binary for
+/// this platform in.
+pub fn arch_dir_name() -> anyhow::Result<&'static str> {
+    Ok(
+        if cfg!(all(
+            target_os = "windows",
+            any(target_arch = "x86", target_arch = "x86_64")
+        )) {
+            "win32"
+        } else if cfg!(all(target_os = "linux", target_arch = "arm")) {
+            "linux-arm32"
+        } else if cfg!(all(target_os = "linux", target_arch = "aarch64")) {
+            "linux-arm64"
+        } else if cfg!(all(
+            target_os = "linux",
+            any(target_arch = "x86", target_arch = "x86_64")
+        )) {
+            "linux-x86"
+        } else if cfg!(target_os = "macos") {
+            "osx"
+        } else {
+            bail!("unsupported operating system")
+        },
+    )
+}
+
+/// The binaries published with every release. Each is installed into `bin`.
+pub const RELEASE_BINARIES: [&str; 3] = ["vexcom", "vexmason", "vexmason-installer"];
+
+/// Gets the name of the release asset holding `binary` for this platform, e.g.
+/// `vexmason-win32.exe` or `vexcom-linux-arm64`.
+pub fn release_asset_name(binary: &str) -> anyhow::Result<String> {
+    Ok(format!(
+        "{}-{}{}",
+        binary,
+        arch_dir_name()?,
+        std::env::consts::EXE_SUFFIX
+    ))
+}
+
+/// Gets the directory VS Code installs extensions into.
+pub fn get_vscode_extensions_directory(reference_path: Option<&Path>) -> anyhow::Result<PathBuf> {
+    Ok(get_user_directory(reference_path)?
+        .join(".vscode")
+        .join("extensions"))
+}
+
+/// Lists every installed version of the VEXcode extension, sorted by name.
+pub async fn find_vexcode_extensions(vscode_extensions_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut dir_iterator = tokio::fs::read_dir(vscode_extensions_dir)
+        .await
+        .with_context(|| "failed to read the VS Code extensions directory")?;
+    let mut extensions = Vec::new();
+    while let Some(entry) = dir_iterator.next_entry().await? {
+        if entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with("vexrobotics.vexcode")
+        {
+            extensions.push(entry.path());
+        }
+    }
+    extensions.sort();
+    Ok(extensions)
+}
+
+/// Gets the directory holding the vexcom binary in a VEXcode extension.
+pub fn vexcom_directory(vexcode_extension: &Path) -> anyhow::Result<PathBuf> {
+    Ok(vexcode_extension
+        .join("resources")
+        .join("tools")
+        .join("vexcom")
+        .join(arch_dir_name()?))
+}
@@ -1,6 +1,16 @@
+pub mod build_cache;
+pub mod bundle;
 pub mod check_versions;
 pub mod compile_file;
 pub mod config;
+pub mod diagnostic;
+pub mod doctor;
+pub mod hook;
+pub mod init;
 pub mod installation_location;
+pub mod minify;
 pub mod modify_args;
+pub mod modules;
+pub mod python;
 pub mod save_readable;
+pub mod source_map;
@@ -0,0 +1,401 @@
+//! A small, conservative Python minifier for the bundled output.
+//!
+//! It works on tokens rather than an AST, so it only does transformations
+//! that are safe without knowing what the code means: it strips comments and
+//! docstrings, drops blank lines, joins lines that are continued with a
+//! backslash or inside brackets, removes whitespace between tokens where it
+//! isn't needed, and re-indents every block with a single space.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TokenKind {
+    Word,
+    String,
+    Op,
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+}
+
+/// a line that ends in a newline outside of any brackets
+#[derive(Debug)]
+struct LogicalLine {
+    /// the 1-based line number in the source where this line starts
+    line: usize,
+    indent: usize,
+    tokens: Vec<Token>,
+}
+
+const STRING_PREFIXES: [&str; 15] = [
+    "r", "u", "b", "f", "br", "rb", "fr", "rf", "t", "tr", "rt", "ur", "bu", "ub", "ru",
+];
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || !c.is_ascii()
+}
+
+fn is_string_prefix(word: &str) -> bool {
+    STRING_PREFIXES.contains(&word.to_ascii_lowercase().as_str())
+}
+
+/// Reads a string literal starting at `chars[start]` (the opening quote) and
+/// returns the index just past its end.
+fn read_string(chars: &[char], start: usize) -> usize {
+    let quote = chars[start];
+    let triple = chars.get(start + 1) == Some(&quote) && chars.get(start + 2) == Some(&quote);
+    let mut i = if triple { start + 3 } else { start + 1 };
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            c if c == quote => {
+                if !triple {
+                    return i + 1;
+                }
+                if chars.get(i + 1) == Some(&quote) && chars.get(i + 2) == Some(&quote) {
+                    return i + 3;
+                }
+                i += 1;
+            }
+            // unterminated single-quoted string; let python complain about it
+            '\n' if !triple => return i,
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+fn tokenize(source: &str) -> Vec<LogicalLine> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut lines = Vec::new();
+    let mut current: Option<LogicalLine> = None;
+    let mut bracket_depth = 0usize;
+    let mut at_line_start = true;
+    let mut line_number = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        if at_line_start {
+            at_line_start = false;
+            if current.is_none() {
+                let mut indent = 0;
+                while i < chars.len() && matches!(chars[i], ' ' | '\t' | '\x0c') {
+                    indent = match chars[i] {
+                        '\t' => (indent / 8 + 1) * 8,
+                        '\x0c' => 0,
+                        _ => indent + 1,
+                    };
+                    i += 1;
+                }
+                match chars.get(i) {
+                    // blank or comment-only lines don't start a logical line
+                    None | Some('\n') | Some('\r') | Some('#') => {}
+                    _ => {
+                        current = Some(LogicalLine {
+                            line: line_number,
+                            indent,
+                            tokens: Vec::new(),
+                        })
+                    }
+                }
+                continue;
+            }
+        }
+
+        let c = chars[i];
+        match c {
+            '\n' => {
+                at_line_start = true;
+                line_number += 1;
+                if bracket_depth == 0 {
+                    if let Some(line) = current.take() {
+                        lines.push(line);
+                    }
+                }
+                i += 1;
+            }
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '\\' if matches!(chars.get(i + 1), Some('\n') | Some('\r')) => {
+                // explicit line continuation: skip the line break entirely
+                i += 1;
+                if chars.get(i) == Some(&'\r') {
+                    i += 1;
+                }
+                if chars.get(i) == Some(&'\n') {
+                    i += 1;
+                }
+                line_number += 1;
+            }
+            ' ' | '\t' | '\r' | '\x0c' => i += 1,
+            '"' | '\'' => {
+                let end = read_string(&chars, i);
+                push_token(&mut current, TokenKind::String, &chars[i..end]);
+                line_number += chars[i..end].iter().filter(|x| **x == '\n').count();
+                i = end;
+            }
+            c if c.is_ascii_digit()
+                || (c == '.' && chars.get(i + 1).is_some_and(|x| x.is_ascii_digit())) =>
+            {
+                let start = i;
+                while i < chars.len() {
+                    let c = chars[i];
+                    if is_word_char(c) || c == '.' {
+                        i += 1;
+                    } else if matches!(c, '+' | '-') && matches!(chars[i - 1], 'e' | 'E') {
+                        // exponent sign, unless this is a hex literal
+                        let text: String = chars[start..i].iter().collect();
+                        if text.starts_with("0x") || text.starts_with("0X") {
+                            break;
+                        }
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                push_token(&mut current, TokenKind::Word, &chars[start..i]);
+            }
+            c if is_word_char(c) => {
+                let start = i;
+                while i < chars.len() && is_word_char(chars[i]) {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                if matches!(chars.get(i), Some('"') | Some('\'')) && is_string_prefix(&word) {
+                    let end = read_string(&chars, i);
+                    push_token(&mut current, TokenKind::String, &chars[start..end]);
+                    line_number += chars[i..end].iter().filter(|x| **x == '\n').count();
+                    i = end;
+                } else {
+                    push_token(&mut current, TokenKind::Word, &chars[start..i]);
+                }
+            }
+            c => {
+                match c {
+                    '(' | '[' | '{' => bracket_depth += 1,
+                    ')' | ']' | '}' => bracket_depth = bracket_depth.saturating_sub(1),
+                    _ => {}
+                }
+                push_token(&mut current, TokenKind::Op, &chars[i..i + 1]);
+                i += 1;
+            }
+        }
+    }
+    if let Some(line) = current.take() {
+        lines.push(line);
+    }
+    lines
+}
+
+fn push_token(line: &mut Option<LogicalLine>, kind: TokenKind, text: &[char]) {
+    // a line can only be missing here if the source starts with a continuation
+    let line = line.get_or_insert_with(|| LogicalLine {
+        line: 1,
+        indent: 0,
+        tokens: Vec::new(),
+    });
+    line.tokens.push(Token {
+        kind,
+        text: text.iter().collect(),
+    });
+}
+
+fn is_number(token: &Token) -> bool {
+    token.kind == TokenKind::Word
+        && token
+            .text
+            .chars()
+            .next()
+            .is_some_and(|x| x.is_ascii_digit() || x == '.')
+}
+
+fn write_line(output: &mut String, depth: usize, tokens: &[Token]) {
+    for _ in 0..depth {
+        output.push(' ');
+    }
+    let mut previous: Option<&Token> = None;
+    for token in tokens {
+        if let Some(previous) = previous {
+            let needs_space = (previous.kind != TokenKind::Op
+                && token.kind != TokenKind::Op
+                && previous.text.chars().last().is_some_and(is_word_char)
+                && token.text.chars().next().is_some_and(is_word_char))
+                // `1 .real` would otherwise become the invalid `1.real`
+                || (is_number(previous) && token.text == ".");
+            if needs_space {
+                output.push(' ');
+            }
+        }
+        output.push_str(&token.text);
+        previous = Some(token);
+    }
+    output.push('\n');
+}
+
+/// Minifies Python source code. The input must be valid Python; invalid input
+/// produces unspecified (but still invalid) output.
+pub fn minify(source: &str) -> String {
+    minify_with_line_map(source).0
+}
+
+/// Like [`minify`], but also returns the 1-based line in `source` that each
+/// line of the output came from, for use in source maps.
+pub fn minify_with_line_map(source: &str) -> (String, Vec<usize>) {
+    let mut output = String::with_capacity(source.len());
+    let mut line_map = Vec::new();
+    let mut indent_stack: Vec<usize> = vec![0];
+    let mut last_opens_block = false;
+    // the depth and source line at which a `pass` needs to be inserted if the
+    // block that a removed docstring was in turns out to be empty
+    let mut pending_pass: Option<(usize, usize)> = None;
+
+    for line in tokenize(source) {
+        while indent_stack.len() > 1 && *indent_stack.last().unwrap() > line.indent {
+            indent_stack.pop();
+        }
+        if *indent_stack.last().unwrap() < line.indent {
+            indent_stack.push(line.indent);
+        }
+        let depth = indent_stack.len() - 1;
+
+        if let Some((pass_depth, pass_line)) = pending_pass.take() {
+            if depth < pass_depth {
+                write_pass(&mut output, pass_depth);
+                line_map.push(pass_line);
+            }
+        }
+
+        // a statement made of only string literals is a docstring (or a
+        // no-op), so it can be removed entirely
+        if line.tokens.iter().all(|x| x.kind == TokenKind::String) {
+            if last_opens_block {
+                pending_pass = Some((depth, line.line));
+            }
+            continue;
+        }
+
+        last_opens_block = line.tokens.last().is_some_and(|x| x.text == ":");
+        write_line(&mut output, depth, &line.tokens);
+        line_map.push(line.line);
+    }
+    if let Some((pass_depth, pass_line)) = pending_pass {
+        write_pass(&mut output, pass_depth);
+        line_map.push(pass_line);
+    }
+    (output, line_map)
+}
+
+fn write_pass(output: &mut String, depth: usize) {
+    write_line(
+        output,
+        depth,
+        &[Token {
+            kind: TokenKind::Word,
+            text: "pass".to_string(),
+        }],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        process::{Command, Stdio},
+    };
+
+    use super::*;
+
+    /// Checks that `source` is valid Python, if `python3` is available.
+    fn assert_parses(source: &str) {
+        let Ok(mut child) = Command::new("python3")
+            .args(["-c", "import ast, sys; ast.parse(sys.stdin.read())"])
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+        else {
+            eprintln!("python3 isn't available, skipping the parse check");
+            return;
+        };
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(source.as_bytes())
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(
+            output.status.success(),
+            "doesn't parse:\n{}\n{}",
+            source,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    fn check(source: &str, expected: &str) {
+        assert_parses(source);
+        let minified = minify(source);
+        assert_eq!(minified, expected);
+        assert_parses(&minified);
+    }
+
+    #[test]
+    fn docstring_only_blocks_get_pass() {
+        check(
+            r#"def f():
+    """doc"""
+
+class A:
+    '''doc'''
+    def g(self):
+        "only"
+x = 1
+"#,
+            "def f():\n pass\nclass A:\n def g(self):\n  pass\nx=1\n",
+        );
+        check("def f():\n    'doc'\n", "def f():\n pass\n");
+    }
+
+    #[test]
+    fn string_prefixes() {
+        check(
+            r#"x = rb'\\d' + f"{y}" + Rb"a" + br'''b'''
+def g():
+    return u'x'
+"#,
+            r#"x=rb'\\d'+f"{y}"+Rb"a"+br'''b'''
+def g():
+ return u'x'
+"#,
+        );
+    }
+
+    #[test]
+    fn line_continuations() {
+        let source = "x = 1 + \\\n    2\ny = (1,\n     2)\nz = [\n  3,  # comment\n]\nw = 4\n";
+        assert_parses(source);
+        let (minified, line_map) = minify_with_line_map(source);
+        assert_eq!(minified, "x=1+2\ny=(1,2)\nz=[3,]\nw=4\n");
+        assert_eq!(line_map, vec![1, 3, 5, 8]);
+        assert_parses(&minified);
+    }
+
+    #[test]
+    fn hex_and_exponent_literals() {
+        check(
+            "a = 0x1e+1\nb = 1e+5\nc = 1.5E-3 - .5\nd = 0XFF-1\n",
+            "a=0x1e+1\nb=1e+5\nc=1.5E-3-.5\nd=0XFF-1\n",
+        );
+    }
+
+    #[test]
+    fn number_followed_by_attribute() {
+        check(
+            "print(1 .real, 0x1f .bit_length())\n",
+            "print(1 .real,0x1f .bit_length())\n",
+        );
+    }
+}
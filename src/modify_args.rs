@@ -10,8 +10,13 @@ pub struct ModifyOptions<'a> {
     pub description: &'a str,
 }
 
-pub fn modify_args<'a>(args: &mut Vec<String>, options: &ModifyOptions<'a>) -> anyhow::Result<()> {
-    for (i, flag) in args.clone().iter().take(args.len() - 1).enumerate() {
+pub fn modify_args(args: &mut [String], options: &ModifyOptions<'_>) -> anyhow::Result<()> {
+    for (i, flag) in args
+        .to_vec()
+        .iter()
+        .take(args.len().saturating_sub(1))
+        .enumerate()
+    {
         // will never iterate over the last argument
         let argument = &mut args[i + 1];
         match flag.as_str() {
@@ -34,7 +39,7 @@ pub fn modify_args<'a>(args: &mut Vec<String>, options: &ModifyOptions<'a>) -> a
     Ok(())
 }
 
-pub fn has_write<'a>(args: &Vec<String>) -> bool {
+pub fn has_write(args: &[String]) -> bool {
     for flag in args {
         if flag == "--write" {
             return true;
@@ -43,8 +48,8 @@ pub fn has_write<'a>(args: &Vec<String>) -> bool {
     false
 }
 
-pub fn entry_point(args: &Vec<String>) -> Option<&Path> {
-    for (i, flag) in args.iter().take(args.len() - 1).enumerate() {
+pub fn entry_point(args: &[String]) -> Option<&Path> {
+    for (i, flag) in args.iter().take(args.len().saturating_sub(1)).enumerate() {
         // will never iterate over the last argument
         if flag == "--write" {
             return Some(Path::new(&args[i + 1]));
@@ -0,0 +1,110 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use lazy_static::lazy_static;
+use regex::Regex;
+use tokio::fs;
+
+lazy_static! {
+    static ref IMPORT_REGEX: Regex = Regex::new(r"^\s*import\s+(?P<names>[^#;]+)").unwrap();
+    static ref FROM_IMPORT_REGEX: Regex =
+        Regex::new(r"^\s*from\s+(?P<module>\.*[\w.]*)\s+import\s+(?P<names>[^#;]+)").unwrap();
+}
+
+/// Finds every local module reachable from `entry_file` through its imports,
+/// including `entry_file` itself. Modules are resolved relative to the
+/// directory of `entry_file`, the same way python-compiler resolves them;
+/// anything that can't be found there (e.g. `vex`) is assumed to be external.
+///
+/// The returned paths are in the order they were discovered.
+pub async fn reachable_modules(entry_file: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let entry_file = dunce::canonicalize(entry_file)
+        .with_context(|| "failed to canonicalize entry file path")?;
+    let base_dir = entry_file
+        .parent()
+        .with_context(|| "entry file has no parent directory")?
+        .to_path_buf();
+
+    let mut found = vec![entry_file.clone()];
+    let mut seen: HashSet<PathBuf> = HashSet::from([entry_file]);
+    let mut index = 0;
+    while index < found.len() {
+        let module = found[index].clone();
+        index += 1;
+        let source = fs::read_to_string(&module)
+            .await
+            .with_context(|| format!("failed to read module {}", module.to_string_lossy()))?;
+        for candidate in imported_paths(&source, &module, &base_dir) {
+            if candidate.is_file() {
+                let candidate = dunce::canonicalize(&candidate)?;
+                if seen.insert(candidate.clone()) {
+                    found.push(candidate);
+                }
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Lists the paths that the imports in `source` could refer to. Not all of
+/// them will exist.
+fn imported_paths(source: &str, module: &Path, base_dir: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for line in source.lines() {
+        if let Some(captures) = FROM_IMPORT_REGEX.captures(line) {
+            let module_name = &captures["module"];
+            let relative_level = module_name.chars().take_while(|x| *x == '.').count();
+            let dir = if relative_level == 0 {
+                base_dir.to_path_buf()
+            } else {
+                let mut dir = module.parent().unwrap_or(base_dir).to_path_buf();
+                for _ in 1..relative_level {
+                    dir.pop();
+                }
+                dir
+            };
+            let module_path: Vec<&str> = module_name[relative_level..]
+                .split('.')
+                .filter(|x| !x.is_empty())
+                .collect();
+            paths.extend(package_paths(&dir, &module_path));
+            // `from package import submodule`
+            for name in import_names(&captures["names"]) {
+                let mut submodule_path = module_path.clone();
+                submodule_path.push(name);
+                paths.extend(module_paths(&dir, &submodule_path));
+            }
+        } else if let Some(captures) = IMPORT_REGEX.captures(line) {
+            for name in import_names(&captures["names"]) {
+                let module_path: Vec<&str> = name.split('.').collect();
+                paths.extend(package_paths(base_dir, &module_path));
+            }
+        }
+    }
+    paths
+}
+
+/// Splits `a as b, c, (d` into `a`, `c` and `d`.
+fn import_names(names: &str) -> impl Iterator<Item = &str> {
+    names
+        .split(',')
+        .filter_map(|x| x.split_whitespace().next())
+        .map(|x| x.trim_matches(|c| c == '(' || c == ')' || c == '\\'))
+        .filter(|x| !x.is_empty() && *x != "*")
+}
+
+/// The paths for a module and every package it's nested in.
+fn package_paths(dir: &Path, module_path: &[&str]) -> Vec<PathBuf> {
+    (1..=module_path.len())
+        .flat_map(|len| module_paths(dir, &module_path[..len]))
+        .collect()
+}
+
+fn module_paths(dir: &Path, module_path: &[&str]) -> [PathBuf; 2] {
+    let mut path = dir.to_path_buf();
+    path.extend(module_path);
+    [path.with_extension("py"), path.join("__init__.py")]
+}
@@ -0,0 +1,120 @@
+use std::{fmt::Display, path::Path};
+
+use log::info;
+
+use crate::check_versions::{check_program_version, VersionError};
+
+/// Picks the Python interpreter, unless the config sets one.
+pub const PYTHON_ENV_VAR: &str = "VEXMASON_PYTHON";
+
+const PYTHON_VERSION_REQUIREMENT: &str = "^3.10";
+
+/// Tried in order when no interpreter is configured. `py -3` is the Python
+/// launcher on Windows, which works even when `python` is the Microsoft Store
+/// stub.
+const CANDIDATES: [&[&str]; 3] = [&["python3"], &["python"], &["py", "-3"]];
+
+/// A Python interpreter that satisfies vexmason's version requirement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PythonInterpreter {
+    pub program: String,
+    /// passed before any other arguments, e.g. `-3` for `py -3`
+    pub args: Vec<String>,
+    pub version: semver::Version,
+}
+
+impl PythonInterpreter {
+    /// Creates a command that runs this interpreter.
+    pub fn command(&self) -> tokio::process::Command {
+        let mut command = tokio::process::Command::new(&self.program);
+        command.args(&self.args);
+        command
+    }
+}
+
+impl Display for PythonInterpreter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+/// Finds the Python interpreter to use. `configured` (from the config) comes
+/// first, then `VEXMASON_PYTHON`, then `python3`, `python` and `py -3` are
+/// tried in that order. A relative path in `configured`, e.g.
+/// `.venv/bin/python`, is relative to `project_root` rather than the current
+/// directory.
+pub fn find_python(
+    configured: Option<&str>,
+    project_root: Option<&Path>,
+) -> Result<PythonInterpreter, VersionError> {
+    let explicit = match configured {
+        Some(command) => Some((command.to_string(), "the config", project_root)),
+        None => std::env::var(PYTHON_ENV_VAR)
+            .ok()
+            .filter(|x| !x.is_empty())
+            .map(|command| (command, PYTHON_ENV_VAR, None)),
+    };
+    if let Some((command, source, base)) = explicit {
+        let resolve = |program: &str| match base {
+            Some(base) if is_relative_path(program) => {
+                base.join(program).to_string_lossy().to_string()
+            }
+            _ => program.to_string(),
+        };
+        // a path to an interpreter might contain spaces
+        let whole = resolve(&command);
+        let (program, args) = if Path::new(&whole).is_file() {
+            (whole, Vec::new())
+        } else {
+            let mut parts = command.split_whitespace();
+            (resolve(parts.next().unwrap_or_default()), parts.collect())
+        };
+        let interpreter = probe(&program, &args)?;
+        info!(
+            "using {} (Python {}) from {}",
+            interpreter, interpreter.version, source
+        );
+        return Ok(interpreter);
+    }
+
+    let mut first_error = None;
+    for candidate in CANDIDATES {
+        match probe(candidate[0], &candidate[1..]) {
+            Ok(interpreter) => {
+                info!("found {} (Python {})", interpreter, interpreter.version);
+                return Ok(interpreter);
+            }
+            Err(VersionError::NotFound { .. }) => {}
+            // e.g. too old, or the Microsoft Store stub
+            Err(err) => {
+                first_error.get_or_insert(err);
+            }
+        }
+    }
+    Err(first_error.unwrap_or(VersionError::NotFound {
+        prog: format!(
+            "python3, python or py -3 (set {} to choose an interpreter)",
+            PYTHON_ENV_VAR
+        ),
+    }))
+}
+
+/// Whether `program` is a relative path like `./python` or `.venv/bin/python`.
+/// Bare names like `python3` are looked up in PATH instead.
+fn is_relative_path(program: &str) -> bool {
+    let path = Path::new(program);
+    path.is_relative() && path.components().count() > 1
+}
+
+fn probe(program: &str, args: &[&str]) -> Result<PythonInterpreter, VersionError> {
+    let version = check_program_version(program, args, "Python ", PYTHON_VERSION_REQUIREMENT)?;
+    Ok(PythonInterpreter {
+        program: program.to_string(),
+        args: args.iter().map(|x| x.to_string()).collect(),
+        version,
+    })
+}
@@ -0,0 +1,347 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::modules::reachable_modules;
+
+pub const SOURCE_MAP_VERSION: u32 = 1;
+
+lazy_static! {
+    static ref TRACEBACK_FRAME_REGEX: Regex =
+        Regex::new(r#"File "(?P<file>[^"]*)", line (?P<line>\d+)"#).unwrap();
+}
+
+/// Maps lines of the compiled output back to the modules they came from.
+///
+/// The mapping is reconstructed by matching the compiled output against the
+/// original sources, so lines that python-compiler generates itself (e.g. the
+/// module wrappers) are left unmapped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceMap {
+    pub version: u32,
+    pub ranges: Vec<SourceMapRange>,
+}
+
+/// A run of consecutive lines in the compiled output that correspond to
+/// consecutive lines in a single source file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SourceMapRange {
+    /// the first line of the range in the compiled output (1-based)
+    pub start: usize,
+    /// the number of lines in the range
+    pub length: usize,
+    /// relative to the project root
+    pub source: PathBuf,
+    /// the line in `source` corresponding to `start` (1-based)
+    pub source_start: usize,
+}
+
+/// Gets the path the source map for a compiled file is written to.
+pub fn source_map_path(output: &Path) -> PathBuf {
+    let mut path = output.as_os_str().to_owned();
+    path.push(".map");
+    path.into()
+}
+
+/// The names the compiled output can have in a traceback: its name in the
+/// build directory, and the name the brain runs it as.
+const COMPILED_FILE_NAMES: [&str; 2] = ["compiled.py", "main.py"];
+
+fn is_compiled_file(file: &str) -> bool {
+    let name = file.rsplit(['/', '\\']).next().unwrap_or(file);
+    COMPILED_FILE_NAMES.contains(&name)
+}
+
+fn normalize(line: &str) -> Option<&str> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        None
+    } else {
+        Some(line)
+    }
+}
+
+impl SourceMap {
+    /// Generates a source map for `output` by reading every module reachable
+    /// from `entry_file`.
+    pub async fn generate(
+        output: &str,
+        entry_file: &Path,
+        project_root: &Path,
+    ) -> anyhow::Result<SourceMap> {
+        let mut sources = Vec::new();
+        for module in reachable_modules(entry_file).await? {
+            let content = fs::read_to_string(&module)
+                .await
+                .with_context(|| format!("failed to read module {}", module.to_string_lossy()))?;
+            let relative = module
+                .strip_prefix(project_root)
+                .map_or_else(|_| module.clone(), Path::to_path_buf);
+            sources.push((relative, content));
+        }
+        Ok(Self::from_sources(output, &sources))
+    }
+
+    /// Generates a source map for `output` from `(path, content)` pairs.
+    pub fn from_sources(output: &str, sources: &[(PathBuf, String)]) -> SourceMap {
+        let source_lines: Vec<Vec<Option<&str>>> = sources
+            .iter()
+            .map(|(_, content)| content.lines().map(normalize).collect())
+            .collect();
+        let mut index: HashMap<&str, Vec<(usize, usize)>> = HashMap::new();
+        for (file, lines) in source_lines.iter().enumerate() {
+            for (line, content) in lines.iter().enumerate() {
+                if let Some(content) = content {
+                    index.entry(content).or_default().push((file, line));
+                }
+            }
+        }
+        // the next non-blank line after `line` in `file`
+        let next_line = |(file, line): (usize, usize)| {
+            (line + 1..source_lines[file].len())
+                .find(|x| source_lines[file][*x].is_some())
+                .map(|x| (file, x))
+        };
+
+        let output_lines: Vec<Option<&str>> = output.lines().map(normalize).collect();
+        let mut mappings: Vec<Option<(usize, usize)>> = Vec::with_capacity(output_lines.len());
+        let mut previous: Option<(usize, usize)> = None;
+        for (i, content) in output_lines.iter().enumerate() {
+            let Some(content) = content else {
+                mappings.push(None);
+                continue;
+            };
+            let expected = previous.and_then(next_line);
+            let mapping = if expected.is_some_and(|(f, l)| source_lines[f][l] == Some(content)) {
+                expected
+            } else if let Some(candidates) = index.get(content) {
+                let next_output = output_lines[i + 1..].iter().find_map(|x| *x);
+                previous
+                    .and_then(|(pf, pl)| {
+                        candidates
+                            .iter()
+                            .filter(|(f, l)| *f == pf && *l > pl)
+                            .min_by_key(|(_, l)| *l)
+                    })
+                    .or_else(|| {
+                        // disambiguate using the following line
+                        candidates.iter().find(|x| {
+                            next_line(**x).map(|(f, l)| source_lines[f][l]) == Some(next_output)
+                        })
+                    })
+                    .or(candidates.first())
+                    .copied()
+            } else {
+                // probably a line with a substituted define
+                expected
+            };
+            if mapping.is_some() {
+                previous = mapping;
+            }
+            mappings.push(mapping);
+        }
+
+        let mut ranges: Vec<SourceMapRange> = Vec::new();
+        for (i, mapping) in mappings.into_iter().enumerate() {
+            let Some((file, line)) = mapping else {
+                continue;
+            };
+            let (output_line, source, source_line) = (i + 1, &sources[file].0, line + 1);
+            if let Some(last) = ranges.last_mut() {
+                if last.source == *source
+                    && last.start + last.length == output_line
+                    && last.source_start + last.length == source_line
+                {
+                    last.length += 1;
+                    continue;
+                }
+            }
+            ranges.push(SourceMapRange {
+                start: output_line,
+                length: 1,
+                source: source.to_owned(),
+                source_start: source_line,
+            });
+        }
+        SourceMap {
+            version: SOURCE_MAP_VERSION,
+            ranges,
+        }
+    }
+
+    /// Looks up the source file and line of a line in the compiled output.
+    pub fn lookup(&self, line: usize) -> Option<(&Path, usize)> {
+        let index = self.ranges.partition_point(|x| x.start + x.length <= line);
+        let range = self.ranges.get(index)?;
+        if range.start <= line {
+            Some((&range.source, range.source_start + (line - range.start)))
+        } else {
+            None
+        }
+    }
+
+    /// Builds a new source map for output that was transformed from the one
+    /// this map describes, where line `n` of the new output came from line
+    /// `line_map[n - 1]` of the old one.
+    pub fn remap(&self, line_map: &[usize]) -> SourceMap {
+        let mut ranges: Vec<SourceMapRange> = Vec::new();
+        for (i, old_line) in line_map.iter().enumerate() {
+            let Some((source, source_line)) = self.lookup(*old_line) else {
+                continue;
+            };
+            let output_line = i + 1;
+            if let Some(last) = ranges.last_mut() {
+                if last.source == source
+                    && last.start + last.length == output_line
+                    && last.source_start + last.length == source_line
+                {
+                    last.length += 1;
+                    continue;
+                }
+            }
+            ranges.push(SourceMapRange {
+                start: output_line,
+                length: 1,
+                source: source.to_owned(),
+                source_start: source_line,
+            });
+        }
+        SourceMap {
+            version: SOURCE_MAP_VERSION,
+            ranges,
+        }
+    }
+
+    pub async fn read(path: &Path) -> anyhow::Result<SourceMap> {
+        let content = fs::read_to_string(path)
+            .await
+            .with_context(|| format!("failed to read source map {}", path.to_string_lossy()))?;
+        serde_json::from_str(&content).with_context(|| "failed to parse source map")
+    }
+
+    pub async fn write(&self, path: &Path) -> anyhow::Result<()> {
+        fs::write(path, serde_json::to_string(self)?)
+            .await
+            .with_context(|| format!("failed to write source map {}", path.to_string_lossy()))
+    }
+
+    /// Rewrites every `File "...", line N` frame of the compiled output in a
+    /// Python traceback to point at the original source file and line. Frames
+    /// in other files, or that can't be mapped, are left as-is.
+    pub fn rewrite_traceback(&self, traceback: &str) -> String {
+        TRACEBACK_FRAME_REGEX
+            .replace_all(traceback, |captures: &regex::Captures<'_>| {
+                Some(&captures["file"])
+                    .filter(|file| is_compiled_file(file))
+                    .and_then(|_| captures["line"].parse().ok())
+                    .and_then(|line| self.lookup(line))
+                    .map_or_else(
+                        || captures[0].to_string(),
+                        |(source, line)| {
+                            format!(
+                                "File \"{}\", line {}",
+                                source.to_string_lossy().replace('\\', "/"),
+                                line
+                            )
+                        },
+                    )
+            })
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::minify::minify_with_line_map;
+
+    fn sources(files: &[(&str, &str)]) -> Vec<(PathBuf, String)> {
+        files
+            .iter()
+            .map(|(path, content)| (PathBuf::from(path), content.to_string()))
+            .collect()
+    }
+
+    fn lookup(map: &SourceMap, line: usize) -> Option<(String, usize)> {
+        map.lookup(line)
+            .map(|(path, line)| (path.to_string_lossy().to_string(), line))
+    }
+
+    fn at(path: &str, line: usize) -> Option<(String, usize)> {
+        Some((path.to_string(), line))
+    }
+
+    #[test]
+    fn duplicate_lines_across_modules() {
+        let sources = sources(&[
+            ("a.py", "def f():\n    pass\n\ndef g(x):\n    return x\n"),
+            ("b.py", "def h():\n    pass\n\ndef i(x):\n    return x\n"),
+        ]);
+        // python-compiler wraps each module in generated code
+        let output = "def __module_a():\n    def f():\n        pass\n    def g(x):\n        return x\ndef __module_b():\n    def h():\n        pass\n    def i(x):\n        return x\n";
+        let map = SourceMap::from_sources(output, &sources);
+        assert_eq!(lookup(&map, 1), None);
+        assert_eq!(lookup(&map, 3), at("a.py", 2));
+        assert_eq!(lookup(&map, 5), at("a.py", 5));
+        assert_eq!(lookup(&map, 6), None);
+        assert_eq!(lookup(&map, 8), at("b.py", 2));
+        assert_eq!(lookup(&map, 10), at("b.py", 5));
+    }
+
+    #[test]
+    fn duplicate_lines_within_a_module() {
+        let sources = sources(&[(
+            "a.py",
+            "if x:\n    y = 1\nelse:\n    y = 2\nif z:\n    y = 1\nelse:\n    y = 2\n",
+        )]);
+        let map = SourceMap::from_sources(&sources[0].1, &sources);
+        for line in 1..=8 {
+            assert_eq!(lookup(&map, line), at("a.py", line));
+        }
+    }
+
+    #[test]
+    fn substituted_define_lines() {
+        let sources = sources(&[(
+            "main.py",
+            "x = 1\nspeed = __SPEED__\nroute = __ROUTE__\nprint(speed)\n",
+        )]);
+        let output = "x = 1\nspeed = 0.5\nroute = \"far\"\nprint(speed)\n";
+        let map = SourceMap::from_sources(output, &sources);
+        for line in 1..=4 {
+            assert_eq!(lookup(&map, line), at("main.py", line));
+        }
+    }
+
+    #[test]
+    fn remap_after_minification() {
+        let source = "\"\"\"docstring\"\"\"\n\ndef f(a,\n      b):\n    # comment\n    return a + b\n\nprint(f(1, 2))\n";
+        let sources = sources(&[("main.py", source)]);
+        let map = SourceMap::from_sources(source, &sources);
+        let (minified, line_map) = minify_with_line_map(source);
+        assert_eq!(minified, "def f(a,b):\n return a+b\nprint(f(1,2))\n");
+        let map = map.remap(&line_map);
+        assert_eq!(lookup(&map, 1), at("main.py", 3));
+        assert_eq!(lookup(&map, 2), at("main.py", 6));
+        assert_eq!(lookup(&map, 3), at("main.py", 8));
+        assert_eq!(lookup(&map, 4), None);
+    }
+
+    #[test]
+    fn rewrite_traceback_only_touches_compiled_frames() {
+        let sources = sources(&[("src/a.py", "def f():\n    raise ValueError()\n")]);
+        let map = SourceMap::from_sources("def f():\n    raise ValueError()\n", &sources);
+        let traceback = "Traceback (most recent call last):\n  File \"/lib/other.py\", line 2, in g\n  File \"/usd/compiled.py\", line 2, in f\n  File \"main.py\", line 2, in f\n";
+        assert_eq!(
+            map.rewrite_traceback(traceback),
+            "Traceback (most recent call last):\n  File \"/lib/other.py\", line 2, in g\n  File \"src/a.py\", line 2, in f\n  File \"src/a.py\", line 2, in f\n"
+        );
+    }
+}